@@ -1,26 +1,485 @@
+use anyhow::{anyhow, Context, Result};
+use axum::http::HeaderValue;
+use chrono_tz::Tz;
+use clap::Parser;
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::HashSet;
 use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+use crate::services::Budget;
+
+/// Settings resolved from, lowest to highest precedence: built-in defaults,
+/// the TOML config file, environment variables and command-line flags.
 pub struct Config {
-    pub projects_path: String,
+    pub bind_address: IpAddr,
     pub port: u16,
+    pub projects_path: String,
+    /// Directory of Claude Code's per-session todo lists.
+    pub todos_path: String,
+    /// Claude CLI cache directory holding the per-project MCP logs.
+    pub claude_cache_path: String,
+    /// VS Code extension storage, readable through the log viewer.
+    pub vscode_logs_path: String,
+    pub pricing_file: Option<String>,
+    pub cache_ttl_secs: u64,
+    /// How long MCP log statistics are reused before the logs are re-read.
+    pub mcp_cache_ttl_secs: u64,
+    pub index_path: Option<String>,
+    pub dedup_messages: bool,
+    /// Timezone days and months are counted in.
+    pub timezone: Tz,
+    /// Origins allowed to call the API; `None` allows any.
+    pub cors_origins: Option<Vec<HeaderValue>>,
+    /// Spend limits, only read from the config file.
+    pub budgets: Vec<Budget>,
+    /// URL budget alerts are posted to.
+    pub alert_webhook: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "Claude Code usage API server")]
+struct Cli {
+    /// TOML config file (also CONFIG_FILE)
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR")]
+    bind: Option<String>,
+    #[arg(short, long)]
+    port: Option<u16>,
+    /// Claude directory holding `projects` and `todos`
+    #[arg(long, value_name = "DIR")]
+    claude_root: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    projects_path: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    todos_path: Option<String>,
+    /// Claude CLI cache directory with the MCP logs
+    #[arg(long, value_name = "DIR")]
+    claude_cache_path: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    vscode_logs_path: Option<String>,
+    #[arg(long, value_name = "FILE")]
+    pricing_file: Option<String>,
+    /// SQLite file to persist parsed messages in
+    #[arg(long, value_name = "FILE")]
+    index_path: Option<String>,
+    #[arg(long, value_name = "SECS")]
+    cache_ttl_secs: Option<u64>,
+    #[arg(long, value_name = "SECS")]
+    mcp_cache_ttl_secs: Option<u64>,
+    /// Drop repeated log lines for one API response
+    #[arg(long, value_name = "BOOL")]
+    dedup_messages: Option<bool>,
+    /// IANA timezone days and months are counted in
+    #[arg(long, value_name = "TZ")]
+    timezone: Option<String>,
+    /// Origin allowed by CORS; repeat for several, or `*` for any
+    #[arg(long = "cors-origin", value_name = "ORIGIN")]
+    cors_origins: Vec<String>,
+    /// URL budget alerts are posted to
+    #[arg(long, value_name = "URL")]
+    alert_webhook: Option<String>,
+}
+
+/// One source of settings. Unset fields fall through to the layer below.
+#[derive(Debug, Default)]
+struct Settings {
+    bind: Option<String>,
+    port: Option<u16>,
+    claude_root: Option<String>,
+    projects_path: Option<String>,
+    todos_path: Option<String>,
+    claude_cache_path: Option<String>,
+    vscode_logs_path: Option<String>,
+    pricing_file: Option<String>,
+    index_path: Option<String>,
+    cache_ttl_secs: Option<u64>,
+    mcp_cache_ttl_secs: Option<u64>,
+    dedup_messages: Option<bool>,
+    timezone: Option<String>,
+    cors_origins: Option<Vec<String>>,
+    budgets: Option<Vec<Budget>>,
+    alert_webhook: Option<String>,
+}
+
+impl Settings {
+    /// `self` with every field set in `over` replaced.
+    fn overlay(self, over: Settings) -> Settings {
+        Settings {
+            bind: over.bind.or(self.bind),
+            port: over.port.or(self.port),
+            claude_root: over.claude_root.or(self.claude_root),
+            projects_path: over.projects_path.or(self.projects_path),
+            todos_path: over.todos_path.or(self.todos_path),
+            claude_cache_path: over.claude_cache_path.or(self.claude_cache_path),
+            vscode_logs_path: over.vscode_logs_path.or(self.vscode_logs_path),
+            pricing_file: over.pricing_file.or(self.pricing_file),
+            index_path: over.index_path.or(self.index_path),
+            cache_ttl_secs: over.cache_ttl_secs.or(self.cache_ttl_secs),
+            mcp_cache_ttl_secs: over.mcp_cache_ttl_secs.or(self.mcp_cache_ttl_secs),
+            dedup_messages: over.dedup_messages.or(self.dedup_messages),
+            timezone: over.timezone.or(self.timezone),
+            cors_origins: over.cors_origins.or(self.cors_origins),
+            budgets: over.budgets.or(self.budgets),
+            alert_webhook: over.alert_webhook.or(self.alert_webhook),
+        }
+    }
+}
+
+/// Layout of the TOML config file; see `config.example.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSettings {
+    server: ServerSection,
+    paths: PathsSection,
+    cache: CacheSection,
+    usage: UsageSection,
+    alerts: AlertsSection,
+    budgets: Option<Vec<Budget>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerSection {
+    bind: Option<String>,
+    port: Option<u16>,
+    cors_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PathsSection {
+    claude_root: Option<String>,
+    projects: Option<String>,
+    todos: Option<String>,
+    claude_cache: Option<String>,
+    vscode_logs: Option<String>,
+    pricing_file: Option<String>,
+    usage_index: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheSection {
+    ttl_secs: Option<u64>,
+    mcp_ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UsageSection {
+    timezone: Option<String>,
+    dedup_messages: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AlertsSection {
+    webhook_url: Option<String>,
+}
+
+impl From<FileSettings> for Settings {
+    fn from(file: FileSettings) -> Self {
+        Settings {
+            bind: file.server.bind,
+            port: file.server.port,
+            cors_origins: file.server.cors_origins,
+            claude_root: file.paths.claude_root,
+            projects_path: file.paths.projects,
+            todos_path: file.paths.todos,
+            claude_cache_path: file.paths.claude_cache,
+            vscode_logs_path: file.paths.vscode_logs,
+            pricing_file: file.paths.pricing_file,
+            index_path: file.paths.usage_index,
+            cache_ttl_secs: file.cache.ttl_secs,
+            mcp_cache_ttl_secs: file.cache.mcp_ttl_secs,
+            timezone: file.usage.timezone,
+            dedup_messages: file.usage.dedup_messages,
+            budgets: file.budgets,
+            alert_webhook: file.alerts.webhook_url,
+        }
+    }
+}
+
+impl From<Cli> for Settings {
+    fn from(cli: Cli) -> Self {
+        Settings {
+            bind: cli.bind,
+            port: cli.port,
+            claude_root: cli.claude_root,
+            projects_path: cli.projects_path,
+            todos_path: cli.todos_path,
+            claude_cache_path: cli.claude_cache_path,
+            vscode_logs_path: cli.vscode_logs_path,
+            pricing_file: cli.pricing_file,
+            index_path: cli.index_path,
+            cache_ttl_secs: cli.cache_ttl_secs,
+            mcp_cache_ttl_secs: cli.mcp_cache_ttl_secs,
+            dedup_messages: cli.dedup_messages,
+            timezone: cli.timezone,
+            cors_origins: (!cli.cors_origins.is_empty()).then_some(cli.cors_origins),
+            budgets: None,
+            alert_webhook: cli.alert_webhook,
+        }
+    }
+}
+
+/// The first of `names` that is set to a non-empty value.
+fn env_var(names: &[&str]) -> Option<(String, String)> {
+    names.iter().find_map(|name| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (name.to_string(), value))
+    })
+}
+
+fn parse_env<T>(names: &[&str], errors: &mut Vec<String>) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let (name, value) = env_var(names)?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            errors.push(format!("{}: invalid value {:?}: {}", name, value, e));
+            None
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn env_settings(errors: &mut Vec<String>) -> Settings {
+    let string = |names: &[&str]| env_var(names).map(|(_, value)| value);
+    let dedup_messages = env_var(&["DEDUP_MESSAGES"]).and_then(|(name, value)| {
+        let parsed = parse_bool(&value);
+        if parsed.is_none() {
+            errors.push(format!("{}: expected true or false, got {:?}", name, value));
+        }
+        parsed
+    });
+
+    Settings {
+        bind: string(&["BIND_ADDRESS"]),
+        port: parse_env(&["PORT", "RUST_BACKEND_PORT"], errors),
+        claude_root: string(&["CLAUDE_ROOT"]),
+        projects_path: string(&["PROJECTS_PATH", "CLAUDE_PROJECTS_PATH"]),
+        todos_path: string(&["TODOS_PATH"]),
+        claude_cache_path: string(&["CLAUDE_CACHE_PATH"]),
+        vscode_logs_path: string(&["VSCODE_LOGS_PATH"]),
+        pricing_file: string(&["PRICING_FILE"]),
+        index_path: string(&["USAGE_INDEX_PATH"]),
+        cache_ttl_secs: parse_env(&["CACHE_TTL_SECS"], errors),
+        mcp_cache_ttl_secs: parse_env(&["MCP_CACHE_TTL_SECS"], errors),
+        dedup_messages,
+        timezone: string(&["TIMEZONE"]),
+        cors_origins: string(&["CORS_ORIGINS"])
+            .map(|origins| origins.split(',').map(|o| o.trim().to_string()).collect()),
+        budgets: None,
+        alert_webhook: string(&["ALERT_WEBHOOK_URL"]),
+    }
+}
+
+fn file_settings(path: &PathBuf) -> Result<Settings> {
+    let content =
+        fs::read_to_string(path).context(format!("Failed to read config file {:?}", path))?;
+    let file: FileSettings =
+        toml::from_str(&content).context(format!("Failed to parse config file {:?}", path))?;
+    Ok(file.into())
+}
+
+/// Replaces a leading `~` with the home directory.
+fn expand_home(path: String, home: Option<&str>) -> String {
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => path,
+    }
 }
 
 impl Config {
-    pub fn from_env() -> Self {
-        let home = env::var("HOME").expect("HOME environment variable not set");
-        let projects_path = env::var("PROJECTS_PATH")
-            .or_else(|_| env::var("CLAUDE_PROJECTS_PATH"))
-            .unwrap_or_else(|_| format!("{}/.claude/projects", home));
-
-        let port = env::var("PORT")
-            .or_else(|_| env::var("RUST_BACKEND_PORT"))
-            .unwrap_or_else(|_| "8080".to_string())
-            .parse()
-            .expect("Invalid port number");
-
-        Config {
-            projects_path,
-            port,
+    /// Reads every layer and validates the result, reporting all problems
+    /// at once. Invalid command-line flags print usage and exit.
+    pub fn load() -> Result<Self> {
+        let cli = Cli::parse();
+        // A .env file in the working directory counts as environment.
+        dotenvy::dotenv().ok();
+
+        let mut errors = Vec::new();
+        let config_file = cli
+            .config
+            .clone()
+            .or_else(|| env_var(&["CONFIG_FILE"]).map(|(_, path)| path.into()));
+        let file = match config_file.as_ref().map(file_settings).transpose() {
+            Ok(file) => file.unwrap_or_default(),
+            Err(e) => {
+                errors.push(format!("{:#}", e));
+                Settings::default()
+            }
+        };
+        let env = env_settings(&mut errors);
+
+        let mut settings = file.overlay(env).overlay(cli.into());
+        // The system TZ is only a default: a configured timezone wins, and
+        // one that is not an IANA name (POSIX rules, `:/etc/localtime`) is
+        // skipped rather than fatal.
+        if settings.timezone.is_none() {
+            settings.timezone = env_var(&["TZ"]).and_then(|(_, tz)| match tz.parse::<Tz>() {
+                Ok(_) => Some(tz),
+                Err(e) => {
+                    tracing::warn!("Ignoring system TZ {:?}: {}; using UTC", tz, e);
+                    None
+                }
+            });
         }
+        Self::resolve(settings, errors)
+    }
+
+    fn resolve(settings: Settings, mut errors: Vec<String>) -> Result<Self> {
+        let home = env::var("HOME").ok().filter(|home| !home.is_empty());
+        let home = home.as_deref();
+        let mut path = |value: Option<String>, what: &str, default: &dyn Fn(&str) -> String| {
+            match (value, home) {
+                (Some(value), _) => expand_home(value, home),
+                (None, Some(home)) => default(home),
+                (None, None) => {
+                    errors.push(format!("HOME is not set; configure the {} path", what));
+                    String::new()
+                }
+            }
+        };
+
+        let claude_root = path(settings.claude_root, "Claude root", &|home| {
+            format!("{}/.claude", home)
+        });
+        let projects_path = settings
+            .projects_path
+            .map(|p| expand_home(p, home))
+            .unwrap_or_else(|| format!("{}/projects", claude_root));
+        let todos_path = settings
+            .todos_path
+            .map(|p| expand_home(p, home))
+            .unwrap_or_else(|| format!("{}/todos", claude_root));
+        let claude_cache_path = path(settings.claude_cache_path, "Claude cache", &|home| {
+            if cfg!(target_os = "macos") {
+                format!("{}/Library/Caches/claude-cli-nodejs", home)
+            } else {
+                format!("{}/.cache/claude-cli-nodejs", home)
+            }
+        });
+        let vscode_logs_path = path(settings.vscode_logs_path, "VS Code logs", &|home| {
+            let storage = "Code/User/globalStorage/saoudrizwan.claude-dev";
+            if cfg!(target_os = "macos") {
+                format!("{}/Library/Application Support/{}", home, storage)
+            } else {
+                format!("{}/.config/{}", home, storage)
+            }
+        });
+
+        let bind = settings.bind.unwrap_or_else(|| "0.0.0.0".to_string());
+        let bind_address = bind.parse().unwrap_or_else(|e| {
+            errors.push(format!("bind address {:?}: {}", bind, e));
+            IpAddr::from([0, 0, 0, 0])
+        });
+
+        let port = settings.port.unwrap_or(8080);
+        if port == 0 {
+            errors.push("port must be between 1 and 65535".to_string());
+        }
+
+        let timezone = settings.timezone.unwrap_or_else(|| "UTC".to_string());
+        let timezone = timezone.parse().unwrap_or_else(|e| {
+            errors.push(format!("timezone {:?}: {}", timezone, e));
+            Tz::UTC
+        });
+
+        let origins = settings.cors_origins.unwrap_or_default();
+        let cors_origins = if origins.is_empty() || origins.iter().any(|o| o == "*") {
+            None
+        } else {
+            let mut values = Vec::new();
+            for origin in &origins {
+                match HeaderValue::from_str(origin) {
+                    Ok(value) if !origin.is_empty() => values.push(value),
+                    _ => errors.push(format!("CORS origin {:?} is not a valid origin", origin)),
+                }
+            }
+            Some(values)
+        };
+
+        let budgets = settings.budgets.unwrap_or_default();
+        let mut budget_names = HashSet::new();
+        for budget in &budgets {
+            if budget.name.is_empty() {
+                errors.push("budgets need a name".to_string());
+            } else if !budget_names.insert(budget.name.as_str()) {
+                errors.push(format!("budget {:?} is defined twice", budget.name));
+            }
+            if !(budget.limit.is_finite() && budget.limit > 0.0) {
+                errors.push(format!("budget {:?}: limit must be positive", budget.name));
+            }
+            if budget
+                .thresholds
+                .iter()
+                .any(|threshold| !(threshold.is_finite() && *threshold > 0.0))
+            {
+                errors.push(format!(
+                    "budget {:?}: thresholds must be positive percentages",
+                    budget.name
+                ));
+            }
+        }
+
+        // Only plain HTTP is supported; the webhook is meant to be local.
+        if let Some(url) = &settings.alert_webhook {
+            match Url::parse(url) {
+                Ok(parsed) if parsed.scheme() == "http" => {}
+                Ok(_) => errors.push(format!("alert webhook {:?} must be an http:// URL", url)),
+                Err(e) => errors.push(format!("alert webhook {:?}: {}", url, e)),
+            }
+        }
+
+        let cache_ttl_secs = settings.cache_ttl_secs.unwrap_or(5);
+
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "Invalid configuration:\n  - {}",
+                errors.join("\n  - ")
+            ));
+        }
+
+        Ok(Config {
+            bind_address,
+            port,
+            projects_path,
+            todos_path,
+            claude_cache_path,
+            vscode_logs_path,
+            pricing_file: settings.pricing_file.map(|p| expand_home(p, home)),
+            cache_ttl_secs,
+            mcp_cache_ttl_secs: settings.mcp_cache_ttl_secs.unwrap_or(cache_ttl_secs),
+            index_path: settings.index_path.map(|p| expand_home(p, home)),
+            dedup_messages: settings.dedup_messages.unwrap_or(true),
+            timezone,
+            cors_origins,
+            budgets,
+            alert_webhook: settings.alert_webhook,
+        })
     }
 }
@@ -0,0 +1,9 @@
+//! Claude Code usage API: session log ingestion, aggregation and the HTTP
+//! routes serving it. The server binary is `main.rs`; the library is also
+//! used by `rust-benchmark` to time the same ingestion code.
+
+pub mod config;
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod state;
@@ -1,19 +1,22 @@
-mod config;
-mod models;
-mod routes;
-mod services;
-
 use axum::{
     routing::get,
     Router,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use config::Config;
-use routes::{get_daily, get_monthly, get_models, get_projects};
+use rust_backend::config::Config;
+use rust_backend::routes::{
+    get_anomalies, get_budgets, get_daily, get_day, get_export, get_forecast, get_hourly,
+    get_log_content, get_mcp_logs, get_mcp_tool, get_mcp_tools, get_metrics, get_models, get_month,
+    get_monthly, get_project, get_projects, get_session, get_sessions, get_stream, get_summary,
+    get_todos, get_tools,
+};
+use rust_backend::services::{spawn_budget_alerts, spawn_watcher};
+use rust_backend::state::AppState;
 
 #[tokio::main]
 async fn main() {
@@ -27,32 +30,84 @@ async fn main() {
         .init();
 
     // Load configuration
-    let config = Arc::new(Config::from_env());
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let addr = SocketAddr::from((config.bind_address, config.port));
     let port = config.port;
+    let allow_origin = match &config.cors_origins {
+        Some(origins) => AllowOrigin::list(origins.iter().cloned()),
+        None => AllowOrigin::from(Any),
+    };
+    let state = match AppState::new(config) {
+        Ok(state) => Arc::new(state),
+        Err(e) => {
+            tracing::error!("Failed to initialize: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Push live updates as session files change
+    if let Err(e) = spawn_watcher(state.clone()) {
+        tracing::warn!("Live updates disabled: {:#}", e);
+    }
+
+    // Raise alerts as budgets reach their thresholds
+    spawn_budget_alerts(state.clone());
 
     // Setup CORS
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods(Any)
         .allow_headers(Any);
 
     // Build our application with routes
     let app = Router::new()
+        .route("/api/v2/anomalies", get(get_anomalies))
+        .route("/api/v2/budgets", get(get_budgets))
         .route("/api/v2/daily", get(get_daily))
+        .route("/api/v2/daily/:date", get(get_day))
+        .route("/api/v2/export/:dataset", get(get_export))
+        .route("/api/v2/forecast", get(get_forecast))
+        .route("/api/v2/hourly", get(get_hourly))
+        .route("/api/v2/logs/content", get(get_log_content))
+        .route("/api/v2/mcp/logs", get(get_mcp_logs))
+        .route("/api/v2/mcp/tools", get(get_mcp_tools))
+        .route("/api/v2/mcp/tools/:toolName", get(get_mcp_tool))
         .route("/api/v2/monthly", get(get_monthly))
+        .route("/api/v2/monthly/:month", get(get_month))
         .route("/api/v2/models", get(get_models))
         .route("/api/v2/projects", get(get_projects))
+        .route("/api/v2/projects/:name", get(get_project))
+        .route("/api/v2/sessions", get(get_sessions))
+        .route("/api/v2/sessions/:id", get(get_session))
+        .route("/api/v2/stream", get(get_stream))
+        .route("/api/v2/summary", get(get_summary))
+        .route("/api/v2/todos", get(get_todos))
+        .route("/api/v2/tools", get(get_tools))
+        .route("/metrics", get(get_metrics))
         .layer(cors)
         .layer(TraceLayer::new_for_http())
-        .with_state(config);
+        .with_state(state);
 
     // Run the server
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
-        .await
-        .unwrap();
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
 
-    tracing::info!("Rust backend listening on port {}", port);
+    tracing::info!("Rust backend listening on {}", addr);
     tracing::info!("Endpoint: http://localhost:{}/api/v2/daily", port);
 
-    axum::serve(listener, app).await.unwrap();
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!("Server error: {}", e);
+        std::process::exit(1);
+    }
 }
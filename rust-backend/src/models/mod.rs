@@ -1,5 +1,11 @@
+use chrono::{DateTime, Utc};
+use serde::de::{Deserializer, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::services::ModelPricing;
 
 #[derive(Debug, Deserialize)]
 pub struct Message {
@@ -7,13 +13,67 @@ pub struct Message {
     pub timestamp: Option<String>,
     #[serde(rename = "sessionId")]
     pub session_id: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
     pub message: Option<MessageContent>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct MessageContent {
+    pub id: Option<String>,
     pub model: Option<String>,
     pub usage: Option<Usage>,
+    /// Content blocks; plain-text content yields none.
+    #[serde(default, deserialize_with = "deserialize_content_blocks")]
+    pub content: Vec<ContentBlock>,
+}
+
+/// A block of message content. Only the fields identifying tool calls and
+/// their results are read; text and tool payloads are skipped.
+#[derive(Debug, Deserialize)]
+pub struct ContentBlock {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// `tool_use` id.
+    pub id: Option<String>,
+    /// `tool_use` tool name.
+    pub name: Option<String>,
+    pub tool_use_id: Option<String>,
+    pub is_error: Option<bool>,
+}
+
+/// `content` is either a string or an array of blocks.
+fn deserialize_content_blocks<'de, D>(deserializer: D) -> Result<Vec<ContentBlock>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct BlocksVisitor;
+
+    impl<'de> Visitor<'de> for BlocksVisitor {
+        type Value = Vec<ContentBlock>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string or an array of content blocks")
+        }
+
+        fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut blocks = Vec::new();
+            while let Some(block) = seq.next_element()? {
+                blocks.push(block);
+            }
+            Ok(blocks)
+        }
+    }
+
+    deserializer.deserialize_any(BlocksVisitor)
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,7 +88,7 @@ pub struct Usage {
     pub cache_read_tokens: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct UsageMetrics {
     pub input_tokens: u64,
     pub output_tokens: u64,
@@ -53,6 +113,9 @@ pub struct DailyUsage {
     #[serde(rename = "totalTokens")]
     pub total_tokens: u64,
     pub cost: String,
+    /// `cost` before rounding, for exports.
+    #[serde(skip)]
+    pub cost_usd: f64,
     pub sessions: usize,
     #[serde(rename = "newInputTokens")]
     pub new_input_tokens: u64,
@@ -66,6 +129,8 @@ pub struct DailyUsage {
 pub struct DailyResponse {
     pub data: Vec<DailyUsage>,
     pub pagination: Pagination,
+    #[serde(rename = "duplicatesDropped")]
+    pub duplicates_dropped: usize,
 }
 
 #[derive(Debug, Serialize)]
@@ -97,6 +162,9 @@ pub struct MonthlyUsage {
     #[serde(rename = "totalTokens")]
     pub total_tokens: u64,
     pub cost: String,
+    /// `cost` before rounding, for exports.
+    #[serde(skip)]
+    pub cost_usd: f64,
     pub sessions: usize,
     pub messages: usize,
     #[serde(rename = "newInputTokens")]
@@ -120,6 +188,9 @@ pub struct ModelUsage {
     #[serde(rename = "totalTokens")]
     pub total_tokens: u64,
     pub cost: String,
+    /// `cost` before rounding, for exports.
+    #[serde(skip)]
+    pub cost_usd: f64,
     pub sessions: usize,
     pub messages: usize,
     #[serde(rename = "newInputTokens")]
@@ -138,13 +209,93 @@ pub struct ProjectData {
     pub total_tokens: u64,
     #[serde(rename = "totalCost")]
     pub total_cost: String,
+    /// `total_cost` before rounding, for exports.
+    #[serde(skip)]
+    pub total_cost_usd: f64,
     #[serde(rename = "messageCount")]
     pub message_count: usize,
     #[serde(rename = "lastActivity")]
     pub last_activity: Option<String>,
 }
 
+// A single message's usage, as pushed to live clients
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageUsage {
+    pub project: String,
+    #[serde(rename = "sessionId")]
+    pub session_id: Option<String>,
+    pub timestamp: String,
+    pub model: String,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: u64,
+    #[serde(rename = "cachedTokens")]
+    pub cached_tokens: u64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+    #[serde(rename = "cacheCreationTokens")]
+    pub cache_creation_tokens: u64,
+    #[serde(rename = "cacheReadTokens")]
+    pub cache_read_tokens: u64,
+}
+
 // Internal data structures
+
+/// One parsed log line that carries a message and a timestamp.
+/// `metrics` is only present for lines that reported token usage.
+#[derive(Debug, Clone)]
+pub struct MessageRecord {
+    pub project: Arc<str>,
+    pub session_id: Option<Arc<str>>,
+    pub timestamp: String,
+    /// `timestamp` parsed, if it is valid RFC 3339.
+    pub sent_at: Option<DateTime<Utc>>,
+    pub model: Arc<str>,
+    pub metrics: Option<UsageMetrics>,
+    /// `message.id:requestId`, identifying an API response that may be
+    /// logged more than once (resumed sessions, copied transcripts).
+    pub dedup_key: Option<Box<str>>,
+    /// Set when an earlier record had the same `dedup_key`.
+    pub duplicate: bool,
+    /// Tools an assistant message invoked.
+    pub tool_uses: Vec<ToolUse>,
+    /// Outcomes of earlier tool calls, reported in a user message.
+    pub tool_results: Vec<ToolResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolUse {
+    pub id: Box<str>,
+    pub name: Box<str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub tool_use_id: Box<str>,
+    pub is_error: bool,
+}
+
+impl MessageRecord {
+    pub fn to_usage(&self) -> Option<MessageUsage> {
+        let metrics = self.metrics.as_ref()?;
+        Some(MessageUsage {
+            project: self.project.to_string(),
+            session_id: self.session_id.as_ref().map(|id| id.to_string()),
+            timestamp: self.timestamp.clone(),
+            model: self.model.to_string(),
+            input_tokens: metrics.input_tokens,
+            output_tokens: metrics.output_tokens,
+            cached_tokens: metrics.cached_tokens,
+            total_tokens: metrics.total_tokens,
+            cost: format!("{:.4}", metrics.cost),
+            cache_creation_tokens: metrics.cache_creation_tokens,
+            cache_read_tokens: metrics.cache_read_tokens,
+        })
+    }
+}
+
 pub struct DayData {
     pub date: String,
     pub input_tokens: u64,
@@ -194,8 +345,267 @@ pub struct ProjectInternal {
     pub last_activity: Option<String>,
 }
 
+impl DayData {
+    pub fn new(date: String) -> Self {
+        DayData {
+            date,
+            input_tokens: 0,
+            output_tokens: 0,
+            cached_tokens: 0,
+            total_tokens: 0,
+            cost: 0.0,
+            sessions: HashSet::new(),
+            new_input_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+        }
+    }
+
+    pub fn add(&mut self, metrics: &UsageMetrics, session_id: Option<&Arc<str>>) {
+        self.input_tokens += metrics.input_tokens;
+        self.output_tokens += metrics.output_tokens;
+        self.cached_tokens += metrics.cached_tokens;
+        self.total_tokens += metrics.total_tokens;
+        self.cost += metrics.cost;
+        self.new_input_tokens += metrics.new_input_tokens;
+        self.cache_creation_tokens += metrics.cache_creation_tokens;
+        self.cache_read_tokens += metrics.cache_read_tokens;
+
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.to_string());
+        }
+    }
+
+    /// Folds another partial aggregate for the same bucket into this one.
+    pub fn merge(&mut self, other: DayData) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost += other.cost;
+        self.new_input_tokens += other.new_input_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.sessions.extend(other.sessions);
+    }
+
+    pub fn to_usage(&self) -> DailyUsage {
+        DailyUsage {
+            date: self.date.clone(),
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: format!("{:.4}", self.cost),
+            cost_usd: self.cost,
+            sessions: self.sessions.len(),
+            new_input_tokens: self.new_input_tokens,
+            cache_creation_tokens: self.cache_creation_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+        }
+    }
+}
+
+impl MonthData {
+    pub fn new(month: String) -> Self {
+        MonthData {
+            month,
+            input_tokens: 0,
+            output_tokens: 0,
+            cached_tokens: 0,
+            total_tokens: 0,
+            cost: 0.0,
+            sessions: HashSet::new(),
+            messages: 0,
+            new_input_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+        }
+    }
+
+    pub fn add(&mut self, metrics: &UsageMetrics, session_id: Option<&Arc<str>>) {
+        self.add_messages(metrics, session_id, 1);
+    }
+
+    /// Adds the summed usage of `messages` messages from one session.
+    pub fn add_messages(
+        &mut self,
+        metrics: &UsageMetrics,
+        session_id: Option<&Arc<str>>,
+        messages: usize,
+    ) {
+        self.input_tokens += metrics.input_tokens;
+        self.output_tokens += metrics.output_tokens;
+        self.cached_tokens += metrics.cached_tokens;
+        self.total_tokens += metrics.total_tokens;
+        self.cost += metrics.cost;
+        self.messages += messages;
+        self.new_input_tokens += metrics.new_input_tokens;
+        self.cache_creation_tokens += metrics.cache_creation_tokens;
+        self.cache_read_tokens += metrics.cache_read_tokens;
+
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.to_string());
+        }
+    }
+
+    /// Folds another partial aggregate for the same bucket into this one.
+    pub fn merge(&mut self, other: MonthData) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost += other.cost;
+        self.messages += other.messages;
+        self.new_input_tokens += other.new_input_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.sessions.extend(other.sessions);
+    }
+
+    pub fn to_usage(&self) -> MonthlyUsage {
+        MonthlyUsage {
+            month: self.month.clone(),
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: format!("{:.4}", self.cost),
+            cost_usd: self.cost,
+            sessions: self.sessions.len(),
+            messages: self.messages,
+            new_input_tokens: self.new_input_tokens,
+            cache_creation_tokens: self.cache_creation_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+        }
+    }
+}
+
+impl ModelData {
+    pub fn new(model: String) -> Self {
+        ModelData {
+            model,
+            input_tokens: 0,
+            output_tokens: 0,
+            cached_tokens: 0,
+            total_tokens: 0,
+            cost: 0.0,
+            sessions: HashSet::new(),
+            messages: 0,
+            new_input_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+        }
+    }
+
+    pub fn add(&mut self, metrics: &UsageMetrics, session_id: Option<&Arc<str>>) {
+        self.add_messages(metrics, session_id, 1);
+    }
+
+    /// Adds the summed usage of `messages` messages from one session.
+    pub fn add_messages(
+        &mut self,
+        metrics: &UsageMetrics,
+        session_id: Option<&Arc<str>>,
+        messages: usize,
+    ) {
+        self.input_tokens += metrics.input_tokens;
+        self.output_tokens += metrics.output_tokens;
+        self.cached_tokens += metrics.cached_tokens;
+        self.total_tokens += metrics.total_tokens;
+        self.cost += metrics.cost;
+        self.messages += messages;
+        self.new_input_tokens += metrics.new_input_tokens;
+        self.cache_creation_tokens += metrics.cache_creation_tokens;
+        self.cache_read_tokens += metrics.cache_read_tokens;
+
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.to_string());
+        }
+    }
+
+    /// Folds another partial aggregate for the same bucket into this one.
+    pub fn merge(&mut self, other: ModelData) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost += other.cost;
+        self.messages += other.messages;
+        self.new_input_tokens += other.new_input_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.sessions.extend(other.sessions);
+    }
+
+    pub fn to_usage(&self) -> ModelUsage {
+        ModelUsage {
+            model: self.model.clone(),
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: format!("{:.4}", self.cost),
+            cost_usd: self.cost,
+            sessions: self.sessions.len(),
+            messages: self.messages,
+            new_input_tokens: self.new_input_tokens,
+            cache_creation_tokens: self.cache_creation_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+        }
+    }
+}
+
+impl ProjectInternal {
+    pub fn new(name: String) -> Self {
+        ProjectInternal {
+            name,
+            total_tokens: 0,
+            total_cost: 0.0,
+            message_count: 0,
+            last_activity: None,
+        }
+    }
+
+    pub fn add(&mut self, record: &MessageRecord) {
+        self.message_count += 1;
+        if self
+            .last_activity
+            .as_ref()
+            .is_none_or(|last| record.timestamp > *last)
+        {
+            self.last_activity = Some(record.timestamp.clone());
+        }
+        if let Some(metrics) = &record.metrics {
+            self.total_tokens += metrics.total_tokens;
+            self.total_cost += metrics.cost;
+        }
+    }
+
+    /// Folds another partial aggregate for the same project into this one.
+    pub fn merge(&mut self, other: ProjectInternal) {
+        self.total_tokens += other.total_tokens;
+        self.total_cost += other.total_cost;
+        self.message_count += other.message_count;
+        if other.last_activity > self.last_activity {
+            self.last_activity = other.last_activity;
+        }
+    }
+
+    pub fn to_data(&self) -> ProjectData {
+        ProjectData {
+            name: self.name.clone(),
+            total_tokens: self.total_tokens,
+            total_cost: format!("{:.4}", self.total_cost),
+            total_cost_usd: self.total_cost,
+            message_count: self.message_count,
+            last_activity: self.last_activity.clone(),
+        }
+    }
+}
+
 impl UsageMetrics {
-    pub fn from_usage(usage: &Usage) -> Self {
+    pub fn from_usage(usage: &Usage, pricing: &ModelPricing) -> Self {
         let input_tokens = usage.input_tokens.unwrap_or(0);
         let output_tokens = usage.output_tokens.unwrap_or(0);
         let cache_creation_tokens = usage.cache_creation_tokens.unwrap_or(0);
@@ -205,10 +615,7 @@ impl UsageMetrics {
         let cached_tokens = cache_creation_tokens + cache_read_tokens;
         let total_tokens = input_tokens + output_tokens + cached_tokens;
 
-        let cost = (input_tokens as f64 * 0.003 / 1000.0)
-            + (output_tokens as f64 * 0.015 / 1000.0)
-            + (cache_creation_tokens as f64 * 0.00375 / 1000.0)
-            + (cache_read_tokens as f64 * 0.0003 / 1000.0);
+        let cost = pricing.cost(usage);
 
         UsageMetrics {
             input_tokens,
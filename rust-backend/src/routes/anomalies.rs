@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    services::{detect_anomalies, AnomalyMetric, AnomalyOptions, Granularity, TimeWindow},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AnomalyParams {
+    /// `daily` (default) or `hourly` buckets.
+    #[serde(default)]
+    granularity: Granularity,
+    /// `cost` (default) or `tokens`.
+    #[serde(default)]
+    metric: AnomalyMetric,
+    /// Preceding buckets each one is compared with; 14 days or 168 hours by
+    /// default.
+    baseline: Option<usize>,
+    /// Robust z-score that flags a bucket.
+    #[serde(default = "default_threshold")]
+    threshold: f64,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    project: Option<String>,
+    model: Option<String>,
+    /// Only flag buckets at or after this date or datetime.
+    from: Option<String>,
+    /// Only flag buckets before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone days and hours are counted in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
+}
+
+fn default_threshold() -> f64 {
+    3.5
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+/// Days or hours whose spend spiked above their rolling baseline, overall
+/// and per project and model.
+pub async fn get_anomalies(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnomalyParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if !(params.threshold.is_finite() && params.threshold > 0.0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "`threshold` must be a positive number".to_string(),
+        ));
+    }
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let options = AnomalyOptions {
+        granularity: params.granularity,
+        metric: params.metric,
+        baseline: params
+            .baseline
+            .unwrap_or(params.granularity.default_baseline())
+            .clamp(3, 1000),
+        threshold: params.threshold,
+        window,
+        project: params.project,
+        model: params.model,
+    };
+
+    let mut report = state
+        .scan_records(move |records| detect_anomalies(records, &options, tz))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    report.anomalies.truncate(params.limit.clamp(1, 1000));
+
+    Ok(Json(report))
+}
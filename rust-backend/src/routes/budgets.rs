@@ -0,0 +1,37 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::{
+    services::{BudgetAlert, BudgetStatus},
+    state::AppState,
+};
+
+#[derive(Serialize)]
+pub struct BudgetsResponse {
+    timezone: String,
+    budgets: Vec<BudgetStatus>,
+    /// Thresholds reached since the backend started, newest first.
+    #[serde(rename = "recentAlerts")]
+    recent_alerts: Vec<BudgetAlert>,
+}
+
+/// Spending against the budgets in the config file for their current
+/// daily, weekly or monthly period.
+pub async fn get_budgets(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let budgets = state
+        .budget_statuses(Utc::now())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let response = BudgetsResponse {
+        timezone: state.tz.name().to_string(),
+        budgets,
+        recent_alerts: state.budget_alerts.recent(),
+    };
+
+    Ok(Json(response))
+}
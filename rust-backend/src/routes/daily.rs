@@ -1,16 +1,18 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::{
-    config::Config,
     models::{DailyResponse, Pagination},
-    services::process_daily_usage,
+    services::{day_detail, TimeWindow},
+    state::AppState,
 };
 
 #[derive(Debug, Deserialize)]
@@ -19,6 +21,12 @@ pub struct DailyParams {
     page: usize,
     #[serde(default = "default_limit")]
     limit: usize,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone days are counted in; defaults to the configured one.
+    tz: Option<Tz>,
 }
 
 fn default_page() -> usize {
@@ -30,16 +38,22 @@ fn default_limit() -> usize {
 }
 
 pub async fn get_daily(
-    State(config): State<Arc<Config>>,
+    State(state): State<Arc<AppState>>,
     Query(params): Query<DailyParams>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let daily_usage = process_daily_usage(&config.projects_path)
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let usage = state
+        .usage_data(tz, &window)
+        .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let daily_usage = usage.daily_usage.clone();
 
     let total_items = daily_usage.len();
-    let items_per_page = params.limit;
+    let items_per_page = params.limit.clamp(1, 1000);
     let current_page = params.page.max(1);
-    let total_pages = (total_items + items_per_page - 1) / items_per_page;
+    let total_pages = total_items.div_ceil(items_per_page);
 
     let start = (current_page - 1) * items_per_page;
     let end = (start + items_per_page).min(total_items);
@@ -60,7 +74,41 @@ pub async fn get_daily(
             has_next: current_page < total_pages,
             has_prev: current_page > 1,
         },
+        duplicates_dropped: usage.duplicates_dropped,
     };
 
     Ok(Json(response))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct DayParams {
+    /// IANA timezone the date is read in; defaults to the configured one.
+    tz: Option<Tz>,
+}
+
+pub async fn get_day(
+    State(state): State<Arc<AppState>>,
+    Path(date): Path<String>,
+    Query(params): Query<DayParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let day = NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Date must be in YYYY-MM-DD format".to_string(),
+        )
+    })?;
+    let tz = params.tz.unwrap_or(state.tz);
+
+    let detail = state
+        .scan_records(move |records| day_detail(records, day, tz))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match detail {
+        Some(detail) => Ok(Json(detail)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No usage data found for {}", date),
+        )),
+    }
+}
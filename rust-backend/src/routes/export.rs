@@ -0,0 +1,181 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    services::{ExportFormat, ExportRows, MessageFilter, TimeWindow},
+    state::AppState,
+};
+
+/// Bytes collected before a chunk is sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks that may wait for a slow client before encoding pauses.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportDataset {
+    Daily,
+    Monthly,
+    Models,
+    Projects,
+    Messages,
+}
+
+impl ExportDataset {
+    fn name(self) -> &'static str {
+        match self {
+            ExportDataset::Daily => "daily",
+            ExportDataset::Monthly => "monthly",
+            ExportDataset::Models => "models",
+            ExportDataset::Projects => "projects",
+            ExportDataset::Messages => "messages",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    /// `csv` (default) or `parquet`.
+    #[serde(default)]
+    format: ExportFormat,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone days and months are counted in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
+    /// Only export messages from this project; `messages` only.
+    project: Option<String>,
+    /// Only export messages from this model; `messages` only.
+    model: Option<String>,
+}
+
+/// Feeds encoded output to the response body in `CHUNK_SIZE` pieces,
+/// blocking while the client is `CHUNKS_IN_FLIGHT` chunks behind.
+pub(crate) struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client went away"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+/// Runs `write` on a blocking thread and sends what it writes as a
+/// response body. Headers are already sent by the time it fails, so an
+/// error cuts the body short instead; `action` names the work in the log.
+pub(crate) fn blocking_body<F>(action: String, write: F) -> Body
+where
+    F: FnOnce(&mut ChannelWriter) -> anyhow::Result<()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter {
+            sender: sender.clone(),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        };
+        let result = write(&mut writer).and_then(|()| Ok(writer.flush()?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to {}: {:#}", action, e);
+            let _ = sender.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+    });
+    Body::from_stream(ReceiverStream::new(receiver))
+}
+
+/// Downloads the daily, monthly, model or project aggregates, or the usage
+/// of every message, as CSV or Parquet. Rows are encoded while the response
+/// is sent rather than built up in memory first.
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    Path(dataset): Path<ExportDataset>,
+    Query(params): Query<ExportParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if dataset != ExportDataset::Messages && (params.project.is_some() || params.model.is_some()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "project and model filters only apply to the messages export".to_string(),
+        ));
+    }
+
+    let rows = match dataset {
+        ExportDataset::Messages => {
+            let snapshot = state
+                .usage_snapshot()
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let filter = MessageFilter {
+                window,
+                project: params.project,
+                model: params.model,
+            };
+            ExportRows::Messages(snapshot, filter)
+        }
+        _ => {
+            let usage = state
+                .usage_data(tz, &window)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            match dataset {
+                ExportDataset::Daily => ExportRows::Daily(usage),
+                ExportDataset::Monthly => ExportRows::Monthly(usage),
+                ExportDataset::Models => ExportRows::Models(usage),
+                _ => ExportRows::Projects(usage),
+            }
+        }
+    };
+
+    let format = params.format;
+    let body = blocking_body(format!("export {}", dataset.name()), move |writer| {
+        rows.write(format, writer)
+    });
+
+    let filename = format!("claude-usage-{}.{}", dataset.name(), format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    ))
+}
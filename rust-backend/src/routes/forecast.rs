@@ -0,0 +1,35 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{services::forecast, state::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastParams {
+    /// IANA timezone days and the month are counted in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
+}
+
+/// End-of-month projection of cost and tokens, overall and per project and
+/// model.
+pub async fn get_forecast(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ForecastParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let now = Utc::now();
+    let report = state
+        .scan_records(move |records| forecast(records, tz, now))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report))
+}
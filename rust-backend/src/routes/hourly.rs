@@ -0,0 +1,54 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    services::{hourly_usage, HourlyFilter, TimeWindow},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct HourlyParams {
+    /// A single day; overrides `startDate` and `endDate`.
+    date: Option<NaiveDate>,
+    #[serde(rename = "startDate")]
+    start_date: Option<NaiveDate>,
+    #[serde(rename = "endDate")]
+    end_date: Option<NaiveDate>,
+    project: Option<String>,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone for dates and hours; defaults to the configured one.
+    tz: Option<Tz>,
+}
+
+pub async fn get_hourly(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HourlyParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let filter = HourlyFilter {
+        window,
+        start_date: params.date.or(params.start_date),
+        end_date: params.date.or(params.end_date),
+        project: params.project,
+    };
+
+    let report = state
+        .scan_records(move |records| hourly_usage(records, &filter, tz))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report))
+}
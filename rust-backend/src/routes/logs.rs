@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use std::io::Write;
+use std::sync::Arc;
+
+use super::export::blocking_body;
+use crate::{
+    services::{resolve_log_path, LogError, LogFile, LogQuery},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct LogContentParams {
+    file: Option<String>,
+    /// Byte offset to continue from, as returned in `nextOffset`.
+    offset: Option<u64>,
+    /// Entry index to start at.
+    line: Option<usize>,
+    /// Most entries to send; they are streamed rather than held, so there
+    /// is no upper bound.
+    #[serde(default = "default_limit")]
+    limit: usize,
+    role: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+fn default_limit() -> usize {
+    200
+}
+
+fn log_error(e: LogError) -> (StatusCode, String) {
+    let status = match e {
+        LogError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        LogError::Forbidden => StatusCode::FORBIDDEN,
+        LogError::NotFound => StatusCode::NOT_FOUND,
+        LogError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        LogError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+/// Streams a page of a session, MCP or VS Code log under one of the
+/// configured Claude directories as it is read, in the shape
+/// `{"success": true, "data": {"path", "size", "modified", "content":
+/// {"content": [...], "parsed"}, "nextOffset", "startLine", "nextLine",
+/// "hasMore"}}` the log viewer renders. The fields after the entries are
+/// only known once they have been read, so they come last.
+pub async fn get_log_content(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LogContentParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let Some(file) = params.file.as_deref().filter(|file| !file.is_empty()) else {
+        return Err((StatusCode::BAD_REQUEST, "File path is required".to_string()));
+    };
+
+    let config = &state.config;
+    let roots = [
+        config.projects_path.as_str(),
+        config.claude_cache_path.as_str(),
+        config.todos_path.as_str(),
+        config.vscode_logs_path.as_str(),
+    ];
+    let path = resolve_log_path(file, &roots).map_err(log_error)?;
+
+    let query = LogQuery {
+        offset: params.offset,
+        line: params.line,
+        limit: params.limit.max(1),
+        role: params.role,
+        kind: params.kind,
+    };
+    let log = LogFile::open(&path, &query).map_err(log_error)?;
+
+    let body = blocking_body(format!("stream {}", path.display()), move |out| {
+        write!(
+            out,
+            "{{\"success\":true,\"data\":{{\"path\":{},\"size\":{},\"modified\":{},\"content\":{{\"content\":[",
+            serde_json::to_string(&path.display().to_string())?,
+            log.size,
+            serde_json::to_string(&log.modified)?,
+        )?;
+        let mut first = true;
+        let end = log.read_page(&query, |entry| {
+            if !first {
+                out.write_all(b",")?;
+            }
+            first = false;
+            serde_json::to_writer(&mut *out, &entry)?;
+            Ok(())
+        })?;
+        write!(
+            out,
+            "],\"parsed\":{}}},\"nextOffset\":{},\"startLine\":{},\"nextLine\":{},\"hasMore\":{}}}}}",
+            end.parsed,
+            serde_json::to_string(&end.next_offset)?,
+            serde_json::to_string(&end.start_line)?,
+            serde_json::to_string(&end.next_line)?,
+            end.has_more,
+        )?;
+        Ok(())
+    });
+
+    Ok(([(header::CONTENT_TYPE, "application/json")], body))
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    models::Pagination,
+    services::{McpLogFile, McpSessionTools, McpToolStats},
+    state::AppState,
+};
+
+/// Sessions listed with a single tool's statistics.
+const RELATED_SESSIONS: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct McpLogsParams {
+    #[serde(default = "default_page")]
+    page: usize,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+    project: Option<String>,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+#[derive(Serialize)]
+pub struct McpLogsResponse {
+    data: Vec<McpLogFile>,
+    pagination: Pagination,
+}
+
+#[derive(Serialize)]
+pub struct McpToolResponse {
+    tool: McpToolStats,
+    #[serde(rename = "relatedSessions")]
+    related_sessions: Vec<McpSessionTools>,
+}
+
+pub async fn get_mcp_logs(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<McpLogsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let report = state
+        .mcp_report()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let logs: Vec<&McpLogFile> = report
+        .logs
+        .iter()
+        .filter(|log| {
+            params
+                .session_id
+                .as_deref()
+                .is_none_or(|id| log.session_id == id)
+                && params
+                    .project
+                    .as_deref()
+                    .is_none_or(|project| log.project == project)
+        })
+        .collect();
+
+    // Apply pagination
+    let total_items = logs.len();
+    let items_per_page = params.limit.clamp(1, 500);
+    let current_page = params.page.max(1);
+    let total_pages = total_items.div_ceil(items_per_page);
+
+    let start = (current_page - 1) * items_per_page;
+    let end = (start + items_per_page).min(total_items);
+
+    let data = if start < total_items {
+        logs[start..end].iter().map(|&log| log.clone()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let response = McpLogsResponse {
+        data,
+        pagination: Pagination {
+            current_page,
+            total_pages,
+            total_items,
+            items_per_page,
+            has_next: current_page < total_pages,
+            has_prev: current_page > 1,
+        },
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn get_mcp_tools(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let report = state
+        .mcp_report()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report.tool_usage.clone()))
+}
+
+pub async fn get_mcp_tool(
+    State(state): State<Arc<AppState>>,
+    Path(tool_name): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let report = state
+        .mcp_report()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let Some(tool) = report
+        .tool_usage
+        .tools
+        .iter()
+        .find(|tool| tool.name == tool_name)
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("No usage data found for tool: {}", tool_name),
+        ));
+    };
+
+    let response = McpToolResponse {
+        tool: tool.clone(),
+        related_sessions: report
+            .sessions_using(&tool_name)
+            .take(RELATED_SESSIONS)
+            .cloned()
+            .collect(),
+    };
+
+    Ok(Json(response))
+}
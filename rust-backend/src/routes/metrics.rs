@@ -0,0 +1,31 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+use crate::{
+    services::{render_metrics, TimeWindow},
+    state::AppState,
+};
+
+/// Prometheus scrape endpoint: all-time usage per project and model, plus
+/// how the usage cache is doing.
+pub async fn get_metrics(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let usage = state
+        .usage_data(state.tz, &TimeWindow::default())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let body = render_metrics(&usage, &state.cache.metrics);
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    ))
+}
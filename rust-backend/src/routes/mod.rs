@@ -1,9 +1,35 @@
+pub mod anomalies;
+pub mod budgets;
 pub mod daily;
+pub mod export;
+pub mod forecast;
+pub mod hourly;
+pub mod logs;
+pub mod mcp;
+pub mod metrics;
 pub mod monthly;
 pub mod models;
 pub mod projects;
+pub mod sessions;
+pub mod stream;
+pub mod summary;
+pub mod todos;
+pub mod tools;
 
+pub use anomalies::*;
+pub use budgets::*;
 pub use daily::*;
+pub use export::*;
+pub use forecast::*;
+pub use hourly::*;
+pub use logs::*;
+pub use mcp::*;
+pub use metrics::*;
 pub use monthly::*;
 pub use models::*;
 pub use projects::*;
+pub use sessions::*;
+pub use stream::*;
+pub use summary::*;
+pub use todos::*;
+pub use tools::*;
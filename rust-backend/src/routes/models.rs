@@ -4,13 +4,15 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::sync::Arc;
 
 use crate::{
-    config::Config,
     models::ModelUsage,
-    services::process_model_usage,
+    services::TimeWindow,
+    state::AppState,
 };
 
 #[derive(Debug, Deserialize)]
@@ -21,6 +23,13 @@ pub struct ModelsParams {
     #[serde(default = "default_sort_order")]
     #[serde(rename = "sortOrder")]
     sort_order: String,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone dates in `from`/`to` are read in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
 }
 
 fn default_sort_by() -> String {
@@ -51,22 +60,30 @@ pub struct ModelsStats {
 pub struct ModelsResponse {
     data: Vec<ModelUsage>,
     stats: ModelsStats,
+    #[serde(rename = "duplicatesDropped")]
+    duplicates_dropped: usize,
 }
 
 pub async fn get_models(
-    State(config): State<Arc<Config>>,
+    State(state): State<Arc<AppState>>,
     Query(params): Query<ModelsParams>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut model_usage = process_model_usage(&config.projects_path)
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let usage = state
+        .usage_data(tz, &window)
+        .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut model_usage = usage.model_usage.clone();
 
     // Apply sorting
     match params.sort_by.as_str() {
         "totalTokens" => {
             if params.sort_order == "asc" {
-                model_usage.sort_by(|a, b| a.total_tokens.cmp(&b.total_tokens));
+                model_usage.sort_by_key(|a| a.total_tokens);
             } else {
-                model_usage.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
+                model_usage.sort_by_key(|b| Reverse(b.total_tokens));
             }
         }
         "cost" => {
@@ -78,23 +95,23 @@ pub async fn get_models(
         }
         "messages" => {
             if params.sort_order == "asc" {
-                model_usage.sort_by(|a, b| a.messages.cmp(&b.messages));
+                model_usage.sort_by_key(|a| a.messages);
             } else {
-                model_usage.sort_by(|a, b| b.messages.cmp(&a.messages));
+                model_usage.sort_by_key(|b| Reverse(b.messages));
             }
         }
         "sessions" => {
             if params.sort_order == "asc" {
-                model_usage.sort_by(|a, b| a.sessions.cmp(&b.sessions));
+                model_usage.sort_by_key(|a| a.sessions);
             } else {
-                model_usage.sort_by(|a, b| b.sessions.cmp(&a.sessions));
+                model_usage.sort_by_key(|b| Reverse(b.sessions));
             }
         }
         "model" => {
             if params.sort_order == "asc" {
-                model_usage.sort_by(|a, b| a.model.to_lowercase().cmp(&b.model.to_lowercase()));
+                model_usage.sort_by_key(|a| a.model.to_lowercase());
             } else {
-                model_usage.sort_by(|a, b| b.model.to_lowercase().cmp(&a.model.to_lowercase()));
+                model_usage.sort_by_key(|b| Reverse(b.model.to_lowercase()));
             }
         }
         _ => {}
@@ -122,6 +139,7 @@ pub async fn get_models(
     let response = ModelsResponse {
         data: model_usage,
         stats,
+        duplicates_dropped: usage.duplicates_dropped,
     };
 
     Ok(Json(response))
@@ -1,16 +1,18 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::{
-    config::Config,
     models::{Pagination, MonthlyUsage},
-    services::process_monthly_usage,
+    services::{month_detail, TimeWindow},
+    state::AppState,
 };
 
 #[derive(Debug, Deserialize)]
@@ -20,6 +22,12 @@ pub struct MonthlyParams {
     #[serde(default = "default_limit")]
     limit: usize,
     year: Option<String>,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone months are counted in; defaults to the configured one.
+    tz: Option<Tz>,
 }
 
 fn default_page() -> usize {
@@ -34,14 +42,22 @@ fn default_limit() -> usize {
 pub struct MonthlyResponse {
     data: Vec<MonthlyUsage>,
     pagination: Pagination,
+    #[serde(rename = "duplicatesDropped")]
+    duplicates_dropped: usize,
 }
 
 pub async fn get_monthly(
-    State(config): State<Arc<Config>>,
+    State(state): State<Arc<AppState>>,
     Query(params): Query<MonthlyParams>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut monthly_usage = process_monthly_usage(&config.projects_path)
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let usage = state
+        .usage_data(tz, &window)
+        .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut monthly_usage = usage.monthly_usage.clone();
 
     // Filter by year if provided
     if let Some(year) = params.year {
@@ -49,9 +65,9 @@ pub async fn get_monthly(
     }
 
     let total_items = monthly_usage.len();
-    let items_per_page = params.limit.max(1).min(120);
+    let items_per_page = params.limit.clamp(1, 120);
     let current_page = params.page.max(1);
-    let total_pages = (total_items + items_per_page - 1) / items_per_page;
+    let total_pages = total_items.div_ceil(items_per_page);
 
     let start = (current_page - 1) * items_per_page;
     let end = (start + items_per_page).min(total_items);
@@ -72,7 +88,44 @@ pub async fn get_monthly(
             has_next: current_page < total_pages,
             has_prev: current_page > 1,
         },
+        duplicates_dropped: usage.duplicates_dropped,
     };
 
     Ok(Json(response))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct MonthParams {
+    /// IANA timezone the month is read in; defaults to the configured one.
+    tz: Option<Tz>,
+}
+
+pub async fn get_month(
+    State(state): State<Arc<AppState>>,
+    Path(month): Path<String>,
+    Query(params): Query<MonthParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let first_day = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .ok()
+        .filter(|_| month.len() == 7)
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Month must be in YYYY-MM format".to_string(),
+            )
+        })?;
+    let tz = params.tz.unwrap_or(state.tz);
+
+    let detail = state
+        .scan_records(move |records| month_detail(records, first_day, tz))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match detail {
+        Some(detail) => Ok(Json(detail)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No usage data found for {}", month),
+        )),
+    }
+}
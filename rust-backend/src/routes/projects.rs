@@ -1,16 +1,18 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::sync::Arc;
 
 use crate::{
-    config::Config,
     models::{Pagination, ProjectData},
-    services::process_projects,
+    services::{project_detail, TimeWindow},
+    state::AppState,
 };
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +28,13 @@ pub struct ProjectsParams {
     #[serde(rename = "minCost")]
     min_cost: Option<f64>,
     search: Option<String>,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone dates in `from`/`to` are read in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
 }
 
 fn default_page() -> usize {
@@ -61,14 +70,22 @@ pub struct ProjectsResponse {
     data: Vec<ProjectData>,
     pagination: Pagination,
     stats: ProjectsStats,
+    #[serde(rename = "duplicatesDropped")]
+    duplicates_dropped: usize,
 }
 
 pub async fn get_projects(
-    State(config): State<Arc<Config>>,
+    State(state): State<Arc<AppState>>,
     Query(params): Query<ProjectsParams>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut projects = process_projects(&config.projects_path)
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let usage = state
+        .usage_data(tz, &window)
+        .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut projects = usage.projects.clone();
 
     // Apply filters
     if let Some(min_cost) = params.min_cost {
@@ -124,23 +141,23 @@ pub async fn get_projects(
         }
         "totalTokens" => {
             if params.sort_order == "asc" {
-                projects.sort_by(|a, b| a.total_tokens.cmp(&b.total_tokens));
+                projects.sort_by_key(|a| a.total_tokens);
             } else {
-                projects.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
+                projects.sort_by_key(|b| Reverse(b.total_tokens));
             }
         }
         "messageCount" => {
             if params.sort_order == "asc" {
-                projects.sort_by(|a, b| a.message_count.cmp(&b.message_count));
+                projects.sort_by_key(|a| a.message_count);
             } else {
-                projects.sort_by(|a, b| b.message_count.cmp(&a.message_count));
+                projects.sort_by_key(|b| Reverse(b.message_count));
             }
         }
         "name" => {
             if params.sort_order == "asc" {
-                projects.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+                projects.sort_by_key(|a| a.name.to_lowercase());
             } else {
-                projects.sort_by(|a, b| b.name.to_lowercase().cmp(&a.name.to_lowercase()));
+                projects.sort_by_key(|b| Reverse(b.name.to_lowercase()));
             }
         }
         _ => {}
@@ -163,9 +180,9 @@ pub async fn get_projects(
 
     // Apply pagination
     let total_items = projects.len();
-    let items_per_page = params.limit.max(1).min(200);
+    let items_per_page = params.limit.clamp(1, 200);
     let current_page = params.page.max(1);
-    let total_pages = (total_items + items_per_page - 1) / items_per_page;
+    let total_pages = total_items.div_ceil(items_per_page);
 
     let start = (current_page - 1) * items_per_page;
     let end = (start + items_per_page).min(total_items);
@@ -187,7 +204,46 @@ pub async fn get_projects(
             has_prev: current_page > 1,
         },
         stats,
+        duplicates_dropped: usage.duplicates_dropped,
     };
 
     Ok(Json(response))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectParams {
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone days are counted in; defaults to the configured one.
+    tz: Option<Tz>,
+}
+
+pub async fn get_project(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<ProjectParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let usage = state
+        .usage_data(state.tz, &TimeWindow::default())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !usage.projects.iter().any(|project| project.name == name) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("No data found for project: {}", name),
+        ));
+    }
+
+    let detail = state
+        .scan_records(move |records| project_detail(records, &name, tz, &window))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(detail))
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use crate::{
+    models::Pagination,
+    services::{session_detail, summarize_sessions, SessionSummary, TimeWindow},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SessionsParams {
+    #[serde(default = "default_page")]
+    page: usize,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(rename = "sortBy", default = "default_sort_by")]
+    sort_by: String,
+    #[serde(rename = "sortOrder", default = "default_sort_order")]
+    sort_order: String,
+    project: Option<String>,
+    model: Option<String>,
+    #[serde(rename = "minCost")]
+    min_cost: Option<f64>,
+    search: Option<String>,
+    /// Only count messages sent at or after this date or datetime.
+    from: Option<String>,
+    /// Only count messages sent before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone dates in `from`/`to` are read in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+fn default_sort_by() -> String {
+    "lastActivity".to_string()
+}
+
+fn default_sort_order() -> String {
+    "desc".to_string()
+}
+
+#[derive(Serialize)]
+pub struct SessionsStats {
+    #[serde(rename = "totalSessions")]
+    total_sessions: usize,
+    #[serde(rename = "totalCost")]
+    total_cost: String,
+    #[serde(rename = "totalTokens")]
+    total_tokens: u64,
+    #[serde(rename = "totalMessages")]
+    total_messages: usize,
+    #[serde(rename = "avgDurationSeconds")]
+    avg_duration_seconds: i64,
+}
+
+#[derive(Serialize)]
+pub struct SessionsResponse {
+    data: Vec<SessionSummary>,
+    pagination: Pagination,
+    stats: SessionsStats,
+}
+
+pub async fn get_sessions(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SessionsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let mut sessions = state
+        .scan_records(move |records| {
+            summarize_sessions(records.iter().copied().filter(|record| {
+                window.contains(record)
+                    && params
+                        .project
+                        .as_deref()
+                        .is_none_or(|project| *record.project == *project)
+            }))
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Apply filters
+    if let Some(model) = &params.model {
+        let model_lower = model.to_lowercase();
+        sessions.retain(|s| {
+            s.models
+                .iter()
+                .any(|m| m.to_lowercase().contains(&model_lower))
+        });
+    }
+
+    if let Some(min_cost) = params.min_cost {
+        sessions.retain(|s| s.cost.parse::<f64>().unwrap_or(0.0) >= min_cost);
+    }
+
+    if let Some(search) = &params.search {
+        let search_lower = search.to_lowercase();
+        sessions.retain(|s| {
+            s.session_id.to_lowercase().contains(&search_lower)
+                || s.project.to_lowercase().contains(&search_lower)
+        });
+    }
+
+    // Apply sorting
+    match params.sort_by.as_str() {
+        "lastActivity" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by(|a, b| a.end_time.cmp(&b.end_time));
+            } else {
+                sessions.sort_by(|a, b| b.end_time.cmp(&a.end_time));
+            }
+        }
+        "startTime" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+            } else {
+                sessions.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+            }
+        }
+        "duration" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by_key(|a| a.duration_seconds);
+            } else {
+                sessions.sort_by_key(|b| Reverse(b.duration_seconds));
+            }
+        }
+        "cost" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by(|a, b| {
+                    a.cost
+                        .parse::<f64>()
+                        .unwrap_or(0.0)
+                        .partial_cmp(&b.cost.parse::<f64>().unwrap_or(0.0))
+                        .unwrap()
+                });
+            } else {
+                sessions.sort_by(|a, b| {
+                    b.cost
+                        .parse::<f64>()
+                        .unwrap_or(0.0)
+                        .partial_cmp(&a.cost.parse::<f64>().unwrap_or(0.0))
+                        .unwrap()
+                });
+            }
+        }
+        "totalTokens" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by_key(|a| a.total_tokens);
+            } else {
+                sessions.sort_by_key(|b| Reverse(b.total_tokens));
+            }
+        }
+        "messageCount" => {
+            if params.sort_order == "asc" {
+                sessions.sort_by_key(|a| a.message_count);
+            } else {
+                sessions.sort_by_key(|b| Reverse(b.message_count));
+            }
+        }
+        _ => {}
+    }
+
+    // Calculate stats
+    let total_cost: f64 = sessions
+        .iter()
+        .map(|s| s.cost.parse::<f64>().unwrap_or(0.0))
+        .sum();
+    let total_tokens: u64 = sessions.iter().map(|s| s.total_tokens).sum();
+    let total_messages: usize = sessions.iter().map(|s| s.message_count).sum();
+    let total_duration: i64 = sessions.iter().map(|s| s.duration_seconds).sum();
+
+    let stats = SessionsStats {
+        total_sessions: sessions.len(),
+        total_cost: format!("{:.2}", total_cost),
+        total_tokens,
+        total_messages,
+        avg_duration_seconds: total_duration / sessions.len().max(1) as i64,
+    };
+
+    // Apply pagination
+    let total_items = sessions.len();
+    let items_per_page = params.limit.clamp(1, 200);
+    let current_page = params.page.max(1);
+    let total_pages = total_items.div_ceil(items_per_page);
+
+    let start = (current_page - 1) * items_per_page;
+    let end = (start + items_per_page).min(total_items);
+
+    let data = if start < total_items {
+        sessions[start..end].to_vec()
+    } else {
+        Vec::new()
+    };
+
+    let response = SessionsResponse {
+        data,
+        pagination: Pagination {
+            current_page,
+            total_pages,
+            total_items,
+            items_per_page,
+            has_next: current_page < total_pages,
+            has_prev: current_page > 1,
+        },
+        stats,
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn get_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let id = session_id.clone();
+    let detail = state
+        .scan_records(move |records| session_detail(records, &id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match detail {
+        Some(detail) => Ok(Json(detail)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No data found for session: {}", session_id),
+        )),
+    }
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::{services::UsageEvent, state::AppState};
+
+/// Server-Sent Events feed of usage changes.
+///
+/// `delta` events carry the new messages and the refreshed daily, model and
+/// project totals they touched. `reset` events mean the aggregates were
+/// rebuilt (or this client fell behind) and everything should be refetched.
+pub async fn get_stream(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.cache.subscribe()).filter_map(|event| {
+        let event = match event {
+            Ok(UsageEvent::Delta(delta)) => {
+                Event::default().event("delta").json_data(&*delta).ok()?
+            }
+            // A lagged receiver has missed deltas, so it must refetch too.
+            Ok(UsageEvent::Reset) | Err(_) => Event::default().event("reset").data("{}"),
+        };
+        Some(Ok(event))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
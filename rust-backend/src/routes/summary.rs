@@ -0,0 +1,185 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Days, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    services::{count_todo_files, TimeWindow},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SummaryParams {
+    /// IANA timezone "today" and "this month" are read in; defaults to the
+    /// configured one.
+    tz: Option<Tz>,
+}
+
+#[derive(Serialize)]
+pub struct PeriodUsage {
+    /// `YYYY-MM-DD` for a day, `YYYY-MM` for a month.
+    period: String,
+    #[serde(rename = "totalTokens")]
+    total_tokens: u64,
+    cost: String,
+    sessions: usize,
+}
+
+impl PeriodUsage {
+    fn new(period: String, total_tokens: u64, cost: &str, sessions: usize) -> Self {
+        PeriodUsage {
+            period,
+            total_tokens,
+            cost: cost.to_string(),
+            sessions,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PeriodChange {
+    tokens: i64,
+    cost: String,
+    /// Cost change relative to the earlier period; `None` when it cost
+    /// nothing.
+    #[serde(rename = "costPercent")]
+    cost_percent: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct SummaryResponse {
+    #[serde(rename = "totalMessages")]
+    total_messages: usize,
+    #[serde(rename = "totalConversations")]
+    total_conversations: usize,
+    #[serde(rename = "totalTokens")]
+    total_tokens: u64,
+    #[serde(rename = "totalCost")]
+    total_cost: String,
+    #[serde(rename = "avgCostPerSession")]
+    avg_cost_per_session: String,
+    /// Share of prompt tokens read from the cache.
+    #[serde(rename = "cacheHitRatio")]
+    cache_hit_ratio: f64,
+    #[serde(rename = "lastActivity")]
+    last_activity: Option<String>,
+    today: PeriodUsage,
+    yesterday: PeriodUsage,
+    #[serde(rename = "thisMonth")]
+    this_month: PeriodUsage,
+    #[serde(rename = "lastMonth")]
+    last_month: PeriodUsage,
+    #[serde(rename = "dayOverDay")]
+    day_over_day: PeriodChange,
+    #[serde(rename = "monthOverMonth")]
+    month_over_month: PeriodChange,
+    #[serde(rename = "duplicatesDropped")]
+    duplicates_dropped: usize,
+    /// MCP log files; the Node summary counts each one as a session.
+    #[serde(rename = "totalMcpSessions")]
+    total_mcp_sessions: usize,
+    #[serde(rename = "totalTodoFiles")]
+    total_todo_files: usize,
+}
+
+fn change(current: &PeriodUsage, previous: &PeriodUsage) -> PeriodChange {
+    let current_cost = current.cost.parse::<f64>().unwrap_or(0.0);
+    let previous_cost = previous.cost.parse::<f64>().unwrap_or(0.0);
+    PeriodChange {
+        tokens: current.total_tokens as i64 - previous.total_tokens as i64,
+        cost: format!("{:.4}", current_cost - previous_cost),
+        cost_percent: (previous_cost > 0.0)
+            .then(|| (current_cost - previous_cost) / previous_cost * 100.0),
+    }
+}
+
+pub async fn get_summary(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SummaryParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let usage = state
+        .usage_data(tz, &TimeWindow::default())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let day_usage = |date: NaiveDate| {
+        let date = date.to_string();
+        match usage.daily_usage.iter().find(|d| d.date == date) {
+            Some(day) => PeriodUsage::new(date, day.total_tokens, &day.cost, day.sessions),
+            None => PeriodUsage::new(date, 0, "0.0000", 0),
+        }
+    };
+    let month_usage = |date: NaiveDate| {
+        let month = date.format("%Y-%m").to_string();
+        match usage.monthly_usage.iter().find(|m| m.month == month) {
+            Some(data) => PeriodUsage::new(month, data.total_tokens, &data.cost, data.sessions),
+            None => PeriodUsage::new(month, 0, "0.0000", 0),
+        }
+    };
+
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    let today_usage = day_usage(today);
+    let yesterday_usage = day_usage(today - Days::new(1));
+    let this_month_usage = month_usage(today);
+    let last_month_usage = month_usage(today - Months::new(1));
+
+    // Lifetime totals
+    let total_messages: usize = usage.monthly_usage.iter().map(|m| m.messages).sum();
+    let total_tokens: u64 = usage.daily_usage.iter().map(|d| d.total_tokens).sum();
+    let total_cost = usage.total_cost;
+    let cache_read_tokens: u64 = usage.daily_usage.iter().map(|d| d.cache_read_tokens).sum();
+    let prompt_tokens: u64 = usage
+        .daily_usage
+        .iter()
+        .map(|d| d.input_tokens + d.cached_tokens)
+        .sum();
+    let total_mcp_sessions = state
+        .mcp_report()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .logs
+        .len();
+    let todos_path = state.config.todos_path.clone();
+    let total_todo_files = tokio::task::spawn_blocking(move || count_todo_files(&todos_path))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|count| count)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let last_activity = usage
+        .projects
+        .iter()
+        .filter_map(|p| p.last_activity.clone())
+        .max();
+
+    let response = SummaryResponse {
+        total_messages,
+        total_conversations: usage.total_sessions,
+        total_tokens,
+        total_cost: format!("{:.2}", total_cost),
+        avg_cost_per_session: format!("{:.4}", total_cost / usage.total_sessions.max(1) as f64),
+        cache_hit_ratio: if prompt_tokens > 0 {
+            cache_read_tokens as f64 / prompt_tokens as f64
+        } else {
+            0.0
+        },
+        last_activity,
+        day_over_day: change(&today_usage, &yesterday_usage),
+        month_over_month: change(&this_month_usage, &last_month_usage),
+        today: today_usage,
+        yesterday: yesterday_usage,
+        this_month: this_month_usage,
+        last_month: last_month_usage,
+        duplicates_dropped: usage.duplicates_dropped,
+        total_mcp_sessions,
+        total_todo_files,
+    };
+
+    Ok(Json(response))
+}
@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    services::{read_todo_files, todo_report, TodoFilter},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TodosParams {
+    project: Option<String>,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+}
+
+pub async fn get_todos(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TodosParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let todos_path = state.config.todos_path.clone();
+    let filter = TodoFilter {
+        project: params.project,
+        session_id: params.session_id,
+    };
+
+    // The todo files are read on the same blocking thread as the records.
+    let report = state
+        .scan_records(move |records| {
+            let files = read_todo_files(&todos_path)?;
+            Ok(todo_report(&files, records, &filter))
+        })
+        .await
+        .and_then(|report| report)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    services::{tool_usage, TimeWindow, ToolFilter},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ToolsParams {
+    project: Option<String>,
+    /// Only count calls made at or after this date or datetime.
+    from: Option<String>,
+    /// Only count calls made before this datetime, or on or before this date.
+    to: Option<String>,
+    /// IANA timezone for dates; defaults to the configured one.
+    tz: Option<Tz>,
+}
+
+pub async fn get_tools(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ToolsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tz = params.tz.unwrap_or(state.tz);
+    let window = TimeWindow::parse(params.from.as_deref(), params.to.as_deref(), tz)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let filter = ToolFilter {
+        window,
+        project: params.project,
+    };
+
+    let report = state
+        .scan_all_records(move |records| tool_usage(records, &filter, tz))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::services::{BudgetPeriod, BudgetStatus};
+use crate::state::AppState;
+
+/// Alerts kept for `/api/v2/budgets`, newest first.
+const RECENT_ALERTS: usize = 50;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A budget threshold reached in the current period.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetAlert {
+    pub budget: String,
+    pub period: BudgetPeriod,
+    #[serde(rename = "periodStart")]
+    pub period_start: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Percentage of the limit, as configured.
+    pub threshold: f64,
+    #[serde(rename = "percentUsed")]
+    pub percent_used: f64,
+    pub spent: String,
+    pub limit: String,
+    /// When the backend first saw the threshold reached.
+    #[serde(rename = "reachedAt")]
+    pub reached_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    alert: &'a BudgetAlert,
+}
+
+#[derive(Default)]
+struct AlertsInner {
+    /// Budget name, period start and threshold of every alert raised.
+    raised: HashSet<(String, String, u64)>,
+    recent: VecDeque<BudgetAlert>,
+}
+
+/// Remembers which thresholds have been reached so each raises one alert
+/// per period. Kept in memory only.
+#[derive(Default)]
+pub struct BudgetAlerts {
+    inner: Mutex<AlertsInner>,
+}
+
+impl BudgetAlerts {
+    /// Alerts for thresholds in `statuses` reached for the first time.
+    pub fn update(&self, statuses: &[BudgetStatus], now: DateTime<Utc>) -> Vec<BudgetAlert> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut alerts = Vec::new();
+        for status in statuses {
+            for &threshold in &status.thresholds_reached {
+                let key = (
+                    status.name.clone(),
+                    status.period_start.clone(),
+                    threshold.to_bits(),
+                );
+                if !inner.raised.insert(key) {
+                    continue;
+                }
+                alerts.push(BudgetAlert {
+                    budget: status.name.clone(),
+                    period: status.period,
+                    period_start: status.period_start.clone(),
+                    project: status.project.clone(),
+                    model: status.model.clone(),
+                    threshold,
+                    percent_used: status.spent_usd / status.limit_usd * 100.0,
+                    spent: status.spent.clone(),
+                    limit: status.limit.clone(),
+                    reached_at: now,
+                });
+            }
+        }
+        for alert in &alerts {
+            inner.recent.push_front(alert.clone());
+        }
+        inner.recent.truncate(RECENT_ALERTS);
+        alerts
+    }
+
+    pub fn recent(&self) -> Vec<BudgetAlert> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.recent.iter().cloned().collect()
+    }
+}
+
+/// Re-checks budgets whenever the usage cache picks up new data and posts
+/// newly reached thresholds to the configured webhook. Thresholds already
+/// reached at startup are recorded without being posted, so restarts do
+/// not repeat them.
+pub fn spawn_budget_alerts(state: Arc<AppState>) {
+    if state.config.budgets.is_empty() {
+        return;
+    }
+    let client = reqwest::Client::new();
+    let mut events = state.cache.subscribe();
+
+    tokio::spawn(async move {
+        let mut notify = false;
+        loop {
+            let now = Utc::now();
+            let result = state
+                .budget_statuses(now)
+                .await
+                .map(|statuses| state.budget_alerts.update(&statuses, now));
+
+            match result {
+                Ok(alerts) => {
+                    for alert in &alerts {
+                        tracing::info!(
+                            "Budget {:?} reached {}% of its limit",
+                            alert.budget,
+                            alert.threshold
+                        );
+                        if let (true, Some(url)) = (notify, &state.config.alert_webhook) {
+                            post_alert(&client, url, alert).await;
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to check budgets: {:#}", e),
+            }
+            notify = true;
+
+            match events.recv().await {
+                // A lagged receiver only missed events; the check reads the
+                // current totals anyway.
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+async fn post_alert(client: &reqwest::Client, url: &str, alert: &BudgetAlert) {
+    let payload = WebhookPayload {
+        event: "budget.threshold",
+        alert,
+    };
+    match client
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&payload)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => tracing::warn!("Budget webhook {} answered {}", url, response.status()),
+        Err(e) => tracing::warn!("Failed to post budget alert to {}: {}", url, e),
+    }
+}
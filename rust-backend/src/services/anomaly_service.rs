@@ -0,0 +1,494 @@
+use crate::models::{DayData, MessageRecord};
+use crate::services::TimeWindow;
+use chrono::{NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Sessions listed with each anomaly, largest first.
+const TOP_SESSIONS: usize = 5;
+
+/// Scales a median absolute deviation to a standard deviation for normally
+/// distributed data.
+const MAD_SCALE: f64 = 1.4826;
+
+/// Least spread assumed, relative to the median, so that steady usage is
+/// not flagged for small changes.
+const MIN_RELATIVE_SPREAD: f64 = 0.1;
+
+/// Active buckets a baseline needs before a bucket is judged by it.
+const MIN_BASELINE: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Daily,
+    Hourly,
+}
+
+impl Granularity {
+    fn bucket(self, local: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Granularity::Daily => local.date().and_time(NaiveTime::MIN),
+            Granularity::Hourly => local
+                .date()
+                .and_hms_opt(local.hour(), 0, 0)
+                .unwrap_or(local),
+        }
+    }
+
+    fn step(self) -> TimeDelta {
+        match self {
+            Granularity::Daily => TimeDelta::days(1),
+            Granularity::Hourly => TimeDelta::hours(1),
+        }
+    }
+
+    fn label(self, bucket: NaiveDateTime) -> String {
+        match self {
+            Granularity::Daily => bucket.format("%Y-%m-%d").to_string(),
+            Granularity::Hourly => bucket.format("%Y-%m-%dT%H:00").to_string(),
+        }
+    }
+
+    /// Buckets in the rolling baseline when none is requested: two weeks
+    /// of days, or one week of hours.
+    pub fn default_baseline(self) -> usize {
+        match self {
+            Granularity::Daily => 14,
+            Granularity::Hourly => 168,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyMetric {
+    #[default]
+    Cost,
+    Tokens,
+}
+
+impl AnomalyMetric {
+    fn value(self, data: &DayData) -> f64 {
+        match self {
+            AnomalyMetric::Cost => data.cost,
+            AnomalyMetric::Tokens => data.total_tokens as f64,
+        }
+    }
+}
+
+/// What to look for anomalies in.
+#[derive(Debug)]
+pub struct AnomalyOptions {
+    pub granularity: Granularity,
+    pub metric: AnomalyMetric,
+    /// Buckets before each one that it is compared with, of which those
+    /// with usage count.
+    pub baseline: usize,
+    /// Robust z-score a bucket must reach to be flagged.
+    pub threshold: f64,
+    /// Only buckets with messages in this window are flagged; earlier
+    /// usage still forms their baseline.
+    pub window: TimeWindow,
+    pub project: Option<String>,
+    pub model: Option<String>,
+}
+
+impl AnomalyOptions {
+    fn matches(&self, record: &MessageRecord) -> bool {
+        self.project
+            .as_deref()
+            .is_none_or(|project| *record.project == *project)
+            && self
+                .model
+                .as_deref()
+                .is_none_or(|model| *record.model == *model)
+    }
+}
+
+/// Range a bucket was expected to fall in, in units of the metric.
+#[derive(Debug, Serialize)]
+pub struct ExpectedRange {
+    pub low: f64,
+    pub median: f64,
+    pub high: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnomalySession {
+    #[serde(rename = "sessionId")]
+    pub session_id: Option<String>,
+    pub project: String,
+    pub messages: usize,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+}
+
+/// A day or hour whose usage spiked above its rolling baseline.
+#[derive(Debug, Serialize)]
+pub struct Anomaly {
+    /// `total`, `project` or `model`.
+    pub scope: &'static str,
+    /// Project or model name; absent for the total.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `YYYY-MM-DD`, or `YYYY-MM-DDTHH:00` for hours, in the report's
+    /// timezone.
+    pub bucket: String,
+    pub value: f64,
+    pub expected: ExpectedRange,
+    /// Distance above the median in robust standard deviations.
+    pub score: f64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+    pub sessions: usize,
+    /// Sessions that contributed the most to the bucket's metric.
+    #[serde(rename = "topSessions")]
+    pub top_sessions: Vec<AnomalySession>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnomalyReport {
+    pub granularity: Granularity,
+    pub metric: AnomalyMetric,
+    pub baseline: usize,
+    pub threshold: f64,
+    /// Buckets checked per scope, including ones without usage.
+    pub buckets: usize,
+    /// Anomalies found, before any limit on those listed.
+    pub total: usize,
+    /// Newest first, then by score.
+    pub anomalies: Vec<Anomaly>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Scope<'a> {
+    Total,
+    Project(&'a str),
+    Model(&'a str),
+}
+
+impl Scope<'_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            Scope::Total => "total",
+            Scope::Project(_) => "project",
+            Scope::Model(_) => "model",
+        }
+    }
+
+    fn name(&self) -> Option<String> {
+        match self {
+            Scope::Total => None,
+            Scope::Project(name) | Scope::Model(name) => Some(name.to_string()),
+        }
+    }
+
+    fn contains(&self, record: &MessageRecord) -> bool {
+        match self {
+            Scope::Total => true,
+            Scope::Project(name) => *record.project == **name,
+            Scope::Model(name) => *record.model == **name,
+        }
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Median and spread of `baseline`: the scaled median absolute deviation,
+/// or the standard deviation when most values are equal, and at least a
+/// tenth of the median.
+fn baseline_stats(baseline: &[f64]) -> (f64, f64) {
+    let center = median(&mut baseline.to_vec());
+    let mut deviations: Vec<f64> = baseline.iter().map(|x| (x - center).abs()).collect();
+    let mad = median(&mut deviations) * MAD_SCALE;
+    let spread = if mad > 0.0 {
+        mad
+    } else {
+        let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+        let variance =
+            baseline.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / baseline.len() as f64;
+        variance.sqrt()
+    };
+    (center, spread.max(center * MIN_RELATIVE_SPREAD))
+}
+
+struct SessionTotals<'a> {
+    project: &'a str,
+    usage: DayData,
+    messages: usize,
+}
+
+fn top_sessions(records: &[&MessageRecord], metric: AnomalyMetric) -> Vec<AnomalySession> {
+    let mut sessions: HashMap<Option<&Arc<str>>, SessionTotals> = HashMap::new();
+    for record in records {
+        let Some(metrics) = &record.metrics else {
+            continue;
+        };
+        let totals = sessions
+            .entry(record.session_id.as_ref())
+            .or_insert_with(|| SessionTotals {
+                project: &record.project,
+                usage: DayData::new(String::new()),
+                messages: 0,
+            });
+        totals.usage.add(metrics, record.session_id.as_ref());
+        totals.messages += 1;
+    }
+
+    let mut sessions: Vec<_> = sessions.into_iter().collect();
+    sessions.sort_by(|(_, a), (_, b)| metric.value(&b.usage).total_cmp(&metric.value(&a.usage)));
+    sessions
+        .into_iter()
+        .take(TOP_SESSIONS)
+        .map(|(session_id, totals)| AnomalySession {
+            session_id: session_id.map(|id| id.to_string()),
+            project: totals.project.to_string(),
+            messages: totals.messages,
+            total_tokens: totals.usage.total_tokens,
+            cost: format!("{:.4}", totals.usage.cost),
+        })
+        .collect()
+}
+
+/// Flags days or hours whose usage rises `threshold` robust standard
+/// deviations above the median of the `baseline` buckets before them, for
+/// all usage and per project and model. Baselines only include buckets with
+/// usage, so idle nights and weekends do not make every active hour or day
+/// stand out. `records` must exclude duplicates.
+pub fn detect_anomalies(
+    records: &[&MessageRecord],
+    options: &AnomalyOptions,
+    tz: Tz,
+) -> AnomalyReport {
+    let granularity = options.granularity;
+    let mut series: HashMap<Scope, BTreeMap<NaiveDateTime, DayData>> = HashMap::new();
+    let mut bucket_records: BTreeMap<NaiveDateTime, Vec<&MessageRecord>> = BTreeMap::new();
+    let mut in_window: Vec<NaiveDateTime> = Vec::new();
+    for record in records {
+        let (Some(metrics), Some(sent_at)) = (&record.metrics, record.sent_at) else {
+            continue;
+        };
+        if !options.matches(record) {
+            continue;
+        }
+        let bucket = granularity.bucket(sent_at.with_timezone(&tz).naive_local());
+        for scope in [
+            Scope::Total,
+            Scope::Project(&record.project),
+            Scope::Model(&record.model),
+        ] {
+            series
+                .entry(scope)
+                .or_default()
+                .entry(bucket)
+                .or_insert_with(|| DayData::new(granularity.label(bucket)))
+                .add(metrics, record.session_id.as_ref());
+        }
+        bucket_records.entry(bucket).or_default().push(record);
+        if options.window.contains(record) {
+            in_window.push(bucket);
+        }
+    }
+    in_window.sort();
+    in_window.dedup();
+
+    // Every scope is checked over the same zero-filled range of buckets.
+    let mut all_buckets = Vec::new();
+    if let (Some(&first), Some(&last)) = (
+        bucket_records.keys().next(),
+        bucket_records.keys().next_back(),
+    ) {
+        let mut bucket = first;
+        while bucket <= last {
+            all_buckets.push(bucket);
+            bucket += granularity.step();
+        }
+    }
+
+    let mut anomalies = Vec::new();
+    for (scope, data) in &series {
+        let values: Vec<f64> = all_buckets
+            .iter()
+            .map(|bucket| data.get(bucket).map_or(0.0, |d| options.metric.value(d)))
+            .collect();
+        for &bucket in &in_window {
+            let Some(day) = data.get(&bucket) else {
+                continue;
+            };
+            let Ok(index) = all_buckets.binary_search(&bucket) else {
+                continue;
+            };
+            let baseline: Vec<f64> = values[index.saturating_sub(options.baseline)..index]
+                .iter()
+                .copied()
+                .filter(|value| *value > 0.0)
+                .collect();
+            if baseline.len() < MIN_BASELINE {
+                continue;
+            }
+            let (center, spread) = baseline_stats(&baseline);
+            let value = values[index];
+            let score = (value - center) / spread;
+            if score < options.threshold {
+                continue;
+            }
+
+            let contributing: Vec<&MessageRecord> = bucket_records[&bucket]
+                .iter()
+                .copied()
+                .filter(|record| scope.contains(record))
+                .collect();
+            anomalies.push((
+                bucket,
+                Anomaly {
+                    scope: scope.kind(),
+                    name: scope.name(),
+                    bucket: granularity.label(bucket),
+                    value,
+                    expected: ExpectedRange {
+                        low: (center - options.threshold * spread).max(0.0),
+                        median: center,
+                        high: center + options.threshold * spread,
+                    },
+                    score,
+                    total_tokens: day.total_tokens,
+                    cost: format!("{:.4}", day.cost),
+                    sessions: day.sessions.len(),
+                    top_sessions: top_sessions(&contributing, options.metric),
+                },
+            ));
+        }
+    }
+    anomalies.sort_by(|(a_bucket, a), (b_bucket, b)| {
+        b_bucket
+            .cmp(a_bucket)
+            .then_with(|| b.score.total_cmp(&a.score))
+    });
+
+    AnomalyReport {
+        granularity,
+        metric: options.metric,
+        baseline: options.baseline,
+        threshold: options.threshold,
+        buckets: all_buckets.len(),
+        total: anomalies.len(),
+        anomalies: anomalies.into_iter().map(|(_, anomaly)| anomaly).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{assert_close, record};
+
+    /// 10:00 UTC on `day` March 2026.
+    fn march(day: u32) -> String {
+        format!("2026-03-{:02}T10:00:00Z", day)
+    }
+
+    fn options() -> AnomalyOptions {
+        AnomalyOptions {
+            granularity: Granularity::Daily,
+            metric: AnomalyMetric::Tokens,
+            baseline: 7,
+            threshold: 3.0,
+            window: TimeWindow::default(),
+            project: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn medians_of_odd_and_even_counts() {
+        assert_close(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_close(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+    }
+
+    #[test]
+    fn spread_falls_back_from_mad_to_deviation_to_a_floor() {
+        // One outlier barely moves the median absolute deviation.
+        let (center, spread) = baseline_stats(&[1.0, 2.0, 3.0, 4.0, 100.0]);
+        assert_close(center, 3.0);
+        assert_close(spread, MAD_SCALE);
+
+        // Mostly equal values have no MAD; the standard deviation is used.
+        let (center, spread) = baseline_stats(&[10.0, 10.0, 10.0, 10.0, 20.0]);
+        assert_close(center, 10.0);
+        assert_close(spread, 4.0);
+
+        // Identical values are given a tenth of the median.
+        let (center, spread) = baseline_stats(&[50.0, 50.0, 50.0]);
+        assert_close(center, 50.0);
+        assert_close(spread, 5.0);
+    }
+
+    #[test]
+    fn flags_a_spike_in_scopes_with_a_baseline() {
+        let mut records: Vec<MessageRecord> = (1..=10)
+            .map(|day| record("steady", &march(day), "claude-sonnet-4-5", 0, 100))
+            .collect();
+        // A small rise, within a tenth of the median.
+        records.push(record("steady", &march(11), "claude-sonnet-4-5", 0, 105));
+        records.push(record("steady", &march(12), "claude-sonnet-4-5", 0, 100));
+        records.push(record("spike", &march(12), "claude-opus-4-1", 0, 900));
+        let records: Vec<&MessageRecord> = records.iter().collect();
+
+        let report = detect_anomalies(&records, &options(), Tz::UTC);
+        assert_eq!(report.buckets, 12);
+
+        let mut scopes: Vec<(&str, Option<&str>)> = report
+            .anomalies
+            .iter()
+            .map(|anomaly| (anomaly.scope, anomaly.name.as_deref()))
+            .collect();
+        scopes.sort();
+        // The opus model has no baseline of its own yet.
+        assert_eq!(
+            scopes,
+            [("project", Some("project")), ("total", None)],
+            "{:?}",
+            report.anomalies
+        );
+
+        let total = report
+            .anomalies
+            .iter()
+            .find(|anomaly| anomaly.scope == "total")
+            .unwrap();
+        assert_eq!(total.bucket, "2026-03-12");
+        assert_close(total.value, 1000.0);
+        assert_close(total.expected.median, 100.0);
+        // The baseline barely deviates, so the spread is its floor of 10.
+        assert_close(total.score, 90.0);
+        assert_eq!(total.top_sessions[0].session_id.as_deref(), Some("spike"));
+        assert_eq!(total.sessions, 2);
+    }
+
+    #[test]
+    fn idle_buckets_stay_out_of_the_baseline() {
+        // Usage every other day: the empty days are not a baseline of zero.
+        let records: Vec<MessageRecord> = [1, 3, 5, 7, 9]
+            .into_iter()
+            .map(|day| record("s1", &march(day), "claude-sonnet-4-5", 0, 100))
+            .collect();
+        let records: Vec<&MessageRecord> = records.iter().collect();
+
+        let report = detect_anomalies(&records, &options(), Tz::UTC);
+        assert_eq!(report.buckets, 9);
+        assert_eq!(report.total, 0);
+    }
+}
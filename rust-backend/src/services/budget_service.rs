@@ -0,0 +1,258 @@
+use crate::models::{DailyUsage, DayData, MessageRecord};
+use crate::services::TimeWindow;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Share of a period that must pass before spending is extrapolated to its
+/// end; earlier, a single message would project far past any limit.
+const MIN_ELAPSED_TO_PROJECT: f64 = 0.2;
+
+/// A spend limit from the `[[budgets]]` tables of the config file. Without
+/// a project or model it covers all usage; with both, only messages
+/// matching both.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    pub name: String,
+    pub period: BudgetPeriod,
+    /// Limit in USD per period.
+    pub limit: f64,
+    pub project: Option<String>,
+    /// A model id, or a prefix of one ending before a `-`
+    /// (`claude-opus-4` covers `claude-opus-4-1-20250805`), in any case.
+    pub model: Option<String>,
+    /// Percentages of the limit that raise an alert when reached.
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<f64>,
+}
+
+fn default_thresholds() -> Vec<f64> {
+    vec![80.0, 100.0]
+}
+
+impl Budget {
+    fn matches(&self, record: &MessageRecord) -> bool {
+        self.project
+            .as_deref()
+            .is_none_or(|project| *record.project == *project)
+            && self.model.as_deref().is_none_or(|model| {
+                // Case-insensitive, as pricing lookups are.
+                let Some(head) = record.model.get(..model.len()) else {
+                    return false;
+                };
+                let rest = &record.model[model.len()..];
+                head.eq_ignore_ascii_case(model) && (rest.is_empty() || rest.starts_with('-'))
+            })
+    }
+}
+
+/// Calendar period a budget resets on. Weeks start on Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// First and last day of the period containing `today`.
+    fn dates(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            BudgetPeriod::Daily => (today, today),
+            BudgetPeriod::Weekly => {
+                let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+                (monday, monday + Days::new(6))
+            }
+            BudgetPeriod::Monthly => {
+                let first = today.with_day(1).unwrap_or(today);
+                (first, first + Months::new(1) - Days::new(1))
+            }
+        }
+    }
+}
+
+/// Where spending stands against a budget in its current period.
+#[derive(Debug, Serialize)]
+pub struct BudgetStatus {
+    pub name: String,
+    pub period: BudgetPeriod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(rename = "periodStart")]
+    pub period_start: String,
+    /// Last day of the period, inclusive.
+    #[serde(rename = "periodEnd")]
+    pub period_end: String,
+    /// Share of the period that has passed, 0 to 1.
+    #[serde(rename = "periodElapsed")]
+    pub period_elapsed: f64,
+    pub limit: String,
+    pub spent: String,
+    /// Negative once the limit is exceeded.
+    pub remaining: String,
+    #[serde(rename = "percentUsed")]
+    pub percent_used: f64,
+    /// Spend at the end of the period if it continues at the current rate;
+    /// just what was spent until a fifth of the period has passed.
+    pub projected: String,
+    /// How far `projected` goes past the limit; zero when it stays under.
+    #[serde(rename = "projectedOverrun")]
+    pub projected_overrun: String,
+    /// `ok`, `atRisk` (projected over the limit) or `exceeded`.
+    pub status: &'static str,
+    /// Configured thresholds reached so far, in percent.
+    #[serde(rename = "thresholdsReached")]
+    pub thresholds_reached: Vec<f64>,
+    /// Days of the period with matching usage, oldest first.
+    pub daily: Vec<DailyUsage>,
+    #[serde(skip)]
+    pub spent_usd: f64,
+    #[serde(skip)]
+    pub limit_usd: f64,
+}
+
+/// Current-period status of every budget at `now`, with periods in `tz`.
+/// `records` must exclude duplicates.
+pub fn budget_statuses(
+    budgets: &[Budget],
+    records: &[&MessageRecord],
+    tz: Tz,
+    now: DateTime<Utc>,
+) -> Vec<BudgetStatus> {
+    let today = now.with_timezone(&tz).date_naive();
+    budgets
+        .iter()
+        .map(|budget| {
+            let (first, last) = budget.period.dates(today);
+            let window = TimeWindow::dates(first, last, tz);
+
+            let mut days: BTreeMap<String, DayData> = BTreeMap::new();
+            for record in records {
+                let Some(metrics) = &record.metrics else {
+                    continue;
+                };
+                let Some(sent_at) = record.sent_at else {
+                    continue;
+                };
+                if !window.contains(record) || !budget.matches(record) {
+                    continue;
+                }
+                let date = sent_at.with_timezone(&tz).date_naive().to_string();
+                days.entry(date.clone())
+                    .or_insert_with(|| DayData::new(date))
+                    .add(metrics, record.session_id.as_ref());
+            }
+
+            // Folded from zero: an empty `sum` of floats is -0.0.
+            let spent = days.values().fold(0.0, |spent, day| spent + day.cost);
+            let period_elapsed = match (window.start, window.end) {
+                (Some(start), Some(end)) if end > start => {
+                    let elapsed = (now - start).num_seconds() as f64;
+                    (elapsed / (end - start).num_seconds() as f64).clamp(0.0, 1.0)
+                }
+                _ => 1.0,
+            };
+            let projected = if period_elapsed >= MIN_ELAPSED_TO_PROJECT {
+                spent / period_elapsed
+            } else {
+                spent
+            };
+            let percent_used = spent / budget.limit * 100.0;
+            let status = if spent >= budget.limit {
+                "exceeded"
+            } else if projected > budget.limit {
+                "atRisk"
+            } else {
+                "ok"
+            };
+
+            BudgetStatus {
+                name: budget.name.clone(),
+                period: budget.period,
+                project: budget.project.clone(),
+                model: budget.model.clone(),
+                period_start: first.to_string(),
+                period_end: last.to_string(),
+                period_elapsed,
+                limit: format!("{:.4}", budget.limit),
+                spent: format!("{:.4}", spent),
+                remaining: format!("{:.4}", budget.limit - spent),
+                percent_used,
+                projected: format!("{:.4}", projected),
+                projected_overrun: format!("{:.4}", (projected - budget.limit).max(0.0)),
+                status,
+                thresholds_reached: budget
+                    .thresholds
+                    .iter()
+                    .copied()
+                    .filter(|&threshold| percent_used >= threshold)
+                    .collect(),
+                daily: days.values().map(DayData::to_usage).collect(),
+                spent_usd: spent,
+                limit_usd: budget.limit,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{record, utc};
+
+    fn budget(model: Option<&str>, limit: f64) -> Budget {
+        Budget {
+            name: "test".to_string(),
+            period: BudgetPeriod::Monthly,
+            limit,
+            project: None,
+            model: model.map(str::to_string),
+            thresholds: default_thresholds(),
+        }
+    }
+
+    #[test]
+    fn matches_models_by_prefix_in_any_case() {
+        let record = record(
+            "s1",
+            "2026-03-02T00:00:00Z",
+            "claude-opus-4-1-20250805",
+            1_000_000,
+            0,
+        );
+        assert!(budget(Some("claude-opus-4"), 1.0).matches(&record));
+        assert!(budget(Some("Claude-Opus-4-1-20250805"), 1.0).matches(&record));
+        assert!(!budget(Some("claude-opus-4-1-2025"), 1.0).matches(&record));
+        assert!(!budget(Some("claude-sonnet-4"), 1.0).matches(&record));
+        assert!(!budget(Some("claude-opus-4-1-20250805-extra"), 1.0).matches(&record));
+    }
+
+    #[test]
+    fn projects_only_once_enough_of_the_period_has_passed() {
+        let record = record(
+            "s1",
+            "2026-03-01T01:00:00Z",
+            "claude-sonnet-4-5",
+            1_000_000,
+            0,
+        );
+        let records = [&record];
+        let budgets = [budget(None, 40.0)];
+        let spent = record.metrics.as_ref().unwrap().cost;
+
+        // Two days into March, the rate so far would project past the limit.
+        let early = &budget_statuses(&budgets, &records, Tz::UTC, utc("2026-03-03T00:00:00Z"))[0];
+        assert_eq!(early.projected, format!("{:.4}", spent));
+        assert_eq!(early.status, "ok");
+
+        // Halfway through, it is doubled.
+        let midway = &budget_statuses(&budgets, &records, Tz::UTC, utc("2026-03-16T12:00:00Z"))[0];
+        assert!((midway.period_elapsed - 0.5).abs() < 1e-9);
+        assert_eq!(midway.projected, format!("{:.4}", spent * 2.0));
+    }
+}
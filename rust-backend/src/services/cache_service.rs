@@ -0,0 +1,733 @@
+use crate::models::MessageRecord;
+use crate::services::{
+    list_project_dirs, list_session_files, project_name, AllProjectData, FileCursor, FileUpdate,
+    FlagUpdate, IngestMetrics, PricingTable, TailOutcome, TimeWindow, UsageAggregates, UsageDelta,
+    UsageStore,
+};
+use anyhow::Result;
+use chrono_tz::Tz;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const EVENT_CAPACITY: usize = 64;
+
+/// Records per partial aggregate when aggregating in parallel.
+const AGGREGATE_CHUNK_RECORDS: usize = 4096;
+
+struct CachedFile {
+    project: Arc<str>,
+    cursor: FileCursor,
+    /// Shared with the published snapshots; only a record whose duplicate
+    /// flag changes is copied.
+    records: Vec<Arc<MessageRecord>>,
+}
+
+/// What a rescan found: records appended to files, which can be merged into
+/// the running aggregates, and whether a change requires rebuilding them.
+#[derive(Default)]
+struct RefreshOutcome {
+    appended: Vec<(PathBuf, usize)>,
+    /// Every file whose cursor moved: index of its first new record and
+    /// whether earlier records were replaced.
+    read: Vec<(PathBuf, usize, bool)>,
+    removed: Vec<PathBuf>,
+    /// Records already read whose duplicate flag changed in a rebuild.
+    reflagged: Vec<(PathBuf, usize)>,
+    rebuild: bool,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    files: HashMap<PathBuf, CachedFile>,
+    project_names: Vec<String>,
+    aggregates: Option<UsageAggregates>,
+    tz: Tz,
+    dedup: bool,
+    /// Dedup keys of every record counted so far, in path order.
+    seen_keys: HashSet<Box<str>>,
+}
+
+/// Parsed records and aggregates as of one rescan. Readers hold on to a
+/// snapshot while the next rescan builds its replacement, so requests never
+/// wait for a rescan they did not need.
+#[derive(Clone)]
+pub struct UsageSnapshot {
+    /// Every record in path order, duplicates included.
+    records: Arc<[Arc<MessageRecord>]>,
+    project_names: Arc<[String]>,
+    tz: Tz,
+    data: Arc<AllProjectData>,
+    checked_at: Instant,
+}
+
+impl UsageSnapshot {
+    /// The prebuilt aggregates, when `window` is unbounded and days and
+    /// months are counted in the cache's own timezone.
+    pub fn cached_data(&self, tz: Tz, window: &TimeWindow) -> Option<Arc<AllProjectData>> {
+        (tz == self.tz && window.is_unbounded()).then(|| self.data.clone())
+    }
+
+    /// Aggregates the records in `window` with days and months in `tz`.
+    pub fn aggregate(&self, tz: Tz, window: &TimeWindow) -> AllProjectData {
+        aggregate(&self.records, &self.project_names, tz, window).to_data()
+    }
+
+    /// Counted records in path order; with `include_duplicates`, records
+    /// dropped as repeats too.
+    pub fn records(&self, include_duplicates: bool) -> Vec<&MessageRecord> {
+        self.records
+            .iter()
+            .filter(|record| include_duplicates || !record.duplicate)
+            .map(|record| &**record)
+            .collect()
+    }
+}
+
+/// Change notifications published whenever the cache picks up new data.
+#[derive(Clone)]
+pub enum UsageEvent {
+    /// Records were appended and merged into the running totals.
+    Delta(Arc<UsageDelta>),
+    /// Aggregates were rebuilt (first load, rewritten or removed files);
+    /// clients should refetch.
+    Reset,
+}
+
+/// Keeps parsed session files and the aggregates built from them in memory.
+///
+/// Rescans run on blocking threads, one at a time, and publish a new
+/// [`UsageSnapshot`] for readers when done. A snapshot older than `ttl` is
+/// refreshed before it is served. Files that grew
+/// are tailed from their last offset and the new records merged into the
+/// running aggregates; aggregates are only rebuilt when a file is rewritten
+/// or removed, or the set of projects changes. Every change is published as
+/// a [`UsageEvent`].
+pub struct UsageCache {
+    ttl: Duration,
+    /// Held for the length of a rescan; readers only take `snapshot`.
+    inner: Mutex<CacheInner>,
+    snapshot: RwLock<Option<Arc<UsageSnapshot>>>,
+    events: broadcast::Sender<UsageEvent>,
+    store: Option<UsageStore>,
+    /// Cleared for good once a write to `store` fails. The index then stops
+    /// being written, so a restart resumes from its last complete state,
+    /// and aggregates are no longer taken from it.
+    store_complete: AtomicBool,
+    /// Rescan, read and lookup counters exposed on `/metrics`.
+    pub metrics: IngestMetrics,
+}
+
+impl UsageCache {
+    /// Aggregates are kept with days and months in `tz`. With `dedup`,
+    /// records repeating the message and request id of an earlier one are
+    /// flagged as duplicates and left out of the totals.
+    pub fn new(ttl: Duration, store: Option<UsageStore>, tz: Tz, dedup: bool) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        UsageCache {
+            ttl,
+            inner: Mutex::new(CacheInner {
+                tz,
+                dedup,
+                ..CacheInner::default()
+            }),
+            snapshot: RwLock::new(None),
+            events,
+            store,
+            store_complete: AtomicBool::new(true),
+            metrics: IngestMetrics::default(),
+        }
+    }
+
+    /// Restores parsed files and read checkpoints from the persistent index,
+    /// so the first rescan only reads what was appended since.
+    pub fn load_store(&self, pricing: &PricingTable) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        for stored in store.load(pricing)? {
+            inner.files.insert(
+                stored.path,
+                CachedFile {
+                    project: stored.project,
+                    cursor: stored.cursor,
+                    records: stored.records.into_iter().map(Arc::new).collect(),
+                },
+            );
+        }
+        tracing::info!(
+            "Restored {} session files from the usage index",
+            inner.files.len()
+        );
+        Ok(())
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UsageEvent> {
+        self.events.subscribe()
+    }
+
+    /// The latest snapshot, unless it is older than the TTL and has to be
+    /// [`refresh`](Self::refresh)ed first. Counted as a cache hit or miss.
+    pub fn fresh_snapshot(&self) -> Option<Arc<UsageSnapshot>> {
+        let snapshot = self.current().filter(|s| s.checked_at.elapsed() < self.ttl);
+        self.metrics.record_lookup(snapshot.is_some());
+        snapshot
+    }
+
+    /// Rescans and returns the new snapshot. Blocks while another rescan
+    /// runs, and skips its own if that one left a fresh snapshot.
+    pub fn refresh(
+        &self,
+        projects_path: &str,
+        pricing: &PricingTable,
+    ) -> Result<Arc<UsageSnapshot>> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(snapshot) = self.current().filter(|s| s.checked_at.elapsed() < self.ttl) {
+            return Ok(snapshot);
+        }
+        self.sync_locked(&mut inner, projects_path, pricing)
+    }
+
+    /// Aggregates the messages in `window` with days and months in `tz`.
+    /// Bounded windows are summed by the usage index when it holds every
+    /// record; otherwise the records of `snapshot` are scanned.
+    pub fn aggregate(
+        &self,
+        snapshot: &UsageSnapshot,
+        tz: Tz,
+        window: &TimeWindow,
+        pricing: &PricingTable,
+    ) -> AllProjectData {
+        let store = self
+            .store
+            .as_ref()
+            .filter(|_| !window.is_unbounded() && self.store_complete.load(Ordering::Relaxed));
+        if let Some(store) = store {
+            match store.aggregate(window, &snapshot.project_names, tz, pricing) {
+                Ok(aggregates) => return aggregates.to_data(),
+                Err(e) => tracing::warn!("Failed to aggregate from the usage index: {:#}", e),
+            }
+        }
+        snapshot.aggregate(tz, window)
+    }
+
+    /// Rescans immediately, ignoring the TTL.
+    pub fn sync(&self, projects_path: &str, pricing: &PricingTable) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        self.sync_locked(&mut inner, projects_path, pricing)?;
+        Ok(())
+    }
+
+    fn current(&self) -> Option<Arc<UsageSnapshot>> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn sync_locked(
+        &self,
+        inner: &mut CacheInner,
+        projects_path: &str,
+        pricing: &PricingTable,
+    ) -> Result<Arc<UsageSnapshot>> {
+        let started = Instant::now();
+        let outcome = inner.sync(projects_path, pricing, &self.metrics)?;
+        self.metrics
+            .record_scan(started.elapsed(), inner.files.len());
+
+        // Written before the snapshot is published, so aggregates taken from
+        // the index include everything the snapshot does.
+        if let Some(store) = self
+            .store
+            .as_ref()
+            .filter(|_| self.store_complete.load(Ordering::Relaxed))
+        {
+            let updates: Vec<FileUpdate> = outcome
+                .read
+                .iter()
+                .map(|(path, start, replace)| {
+                    let cached = &inner.files[path];
+                    FileUpdate {
+                        path,
+                        project: &cached.project,
+                        cursor: &cached.cursor,
+                        first_seq: *start,
+                        records: &cached.records[*start..],
+                        replace: *replace,
+                    }
+                })
+                .collect();
+            let flags: Vec<FlagUpdate> = outcome
+                .reflagged
+                .iter()
+                .map(|(path, seq)| FlagUpdate {
+                    path,
+                    seq: *seq,
+                    duplicate: inner.files[path].records[*seq].duplicate,
+                })
+                .collect();
+            if let Err(e) = store.save(&updates, &flags, &outcome.removed) {
+                tracing::warn!("Failed to update usage index: {:#}", e);
+                self.store_complete.store(false, Ordering::Relaxed);
+            }
+        }
+
+        let snapshot = match self.current() {
+            Some(previous) if !outcome.changed() => Arc::new(UsageSnapshot {
+                checked_at: Instant::now(),
+                ..(*previous).clone()
+            }),
+            _ => Arc::new(inner.snapshot()),
+        };
+        *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = Some(snapshot.clone());
+
+        // Published after the snapshot, so clients refetching on an event
+        // see the new data. Sending only fails when nobody is subscribed.
+        if outcome.rebuild {
+            let _ = self.events.send(UsageEvent::Reset);
+        } else if !outcome.appended.is_empty() && self.events.receiver_count() > 0 {
+            let records: Vec<&MessageRecord> = outcome
+                .appended
+                .iter()
+                .flat_map(|(path, start)| &inner.files[path].records[*start..])
+                .map(|record| &**record)
+                .collect();
+            let aggregates = inner.aggregates.as_ref().expect("aggregates are built");
+            let delta = aggregates.delta_for(&records);
+            let _ = self.events.send(UsageEvent::Delta(Arc::new(delta)));
+        }
+
+        Ok(snapshot)
+    }
+}
+
+impl RefreshOutcome {
+    /// Whether any counted record was added, replaced or removed.
+    fn changed(&self) -> bool {
+        self.rebuild || !self.appended.is_empty()
+    }
+}
+
+impl CacheInner {
+    fn sync(
+        &mut self,
+        projects_path: &str,
+        pricing: &PricingTable,
+        metrics: &IngestMetrics,
+    ) -> Result<RefreshOutcome> {
+        let mut outcome = self.refresh(projects_path, pricing, metrics)?;
+        self.apply(&mut outcome);
+        Ok(outcome)
+    }
+
+    /// Every cached record in path order.
+    fn ordered_records(&self) -> Vec<Arc<MessageRecord>> {
+        let mut paths: Vec<&PathBuf> = self.files.keys().collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .flat_map(|path| self.files[path].records.iter().cloned())
+            .collect()
+    }
+
+    fn snapshot(&self) -> UsageSnapshot {
+        let aggregates = self.aggregates.as_ref().expect("aggregates are built");
+        UsageSnapshot {
+            records: self.ordered_records().into(),
+            project_names: self.project_names.clone().into(),
+            tz: self.tz,
+            data: Arc::new(aggregates.to_data()),
+            checked_at: Instant::now(),
+        }
+    }
+
+    /// Brings `files` in line with the disk, reading only appended bytes.
+    fn refresh(
+        &mut self,
+        projects_path: &str,
+        pricing: &PricingTable,
+        metrics: &IngestMetrics,
+    ) -> Result<RefreshOutcome> {
+        let mut outcome = RefreshOutcome::default();
+
+        let project_dirs = list_project_dirs(projects_path)?;
+        let project_names: Vec<String> = project_dirs.iter().map(|dir| project_name(dir)).collect();
+        if project_names != self.project_names {
+            outcome.rebuild = true;
+            self.project_names = project_names;
+        }
+
+        // Stat every file and collect the ones that need reading.
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        let mut pending: Vec<(PathBuf, Arc<str>, FileCursor)> = Vec::new();
+        for project_dir in &project_dirs {
+            let project: Arc<str> = project_name(project_dir).into();
+
+            for file in list_session_files(project_dir)? {
+                let metadata = match fs::metadata(&file) {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                seen.insert(file.clone());
+
+                let cursor = self
+                    .files
+                    .get(&file)
+                    .map(|cached| cached.cursor.clone())
+                    .unwrap_or_default();
+                if !cursor.is_current(metadata.modified().ok(), metadata.len()) {
+                    pending.push((file, project.clone(), cursor));
+                }
+            }
+        }
+
+        // Parse changed files in parallel; results keep the listing order.
+        let results: Vec<_> = pending
+            .into_par_iter()
+            .map(|(file, project, mut cursor)| {
+                let tail = cursor.read_appended(&file, &project, pricing, metrics);
+                (file, project, cursor, tail)
+            })
+            .collect();
+
+        for (file, project, cursor, tail) in results {
+            // A file that cannot be read keeps its old cursor and records
+            // and is retried on the next pass.
+            let tail = match tail {
+                Ok(tail) => tail,
+                Err(e) => {
+                    tracing::warn!("Failed to read {:?}: {:#}", file, e);
+                    continue;
+                }
+            };
+            let cached = self
+                .files
+                .entry(file.clone())
+                .or_insert_with(|| CachedFile {
+                    project,
+                    cursor: FileCursor::default(),
+                    records: Vec::new(),
+                });
+            cached.cursor = cursor;
+
+            match tail {
+                TailOutcome::Unchanged => {}
+                TailOutcome::Appended(records) => {
+                    let start = cached.records.len();
+                    if !records.is_empty() {
+                        outcome.appended.push((file.clone(), start));
+                        cached.records.extend(records.into_iter().map(Arc::new));
+                    }
+                    outcome.read.push((file, start, false));
+                }
+                TailOutcome::Rewritten(records) => {
+                    cached.records = records.into_iter().map(Arc::new).collect();
+                    outcome.read.push((file, 0, true));
+                    outcome.rebuild = true;
+                }
+            }
+        }
+
+        outcome.removed = self
+            .files
+            .keys()
+            .filter(|path| !seen.contains(*path))
+            .cloned()
+            .collect();
+        for path in &outcome.removed {
+            self.files.remove(path);
+        }
+        outcome.rebuild |= !outcome.removed.is_empty();
+
+        Ok(outcome)
+    }
+
+    /// Folds a refresh into the aggregates.
+    fn apply(&mut self, outcome: &mut RefreshOutcome) {
+        match &mut self.aggregates {
+            Some(aggregates) if !outcome.rebuild => {
+                for (path, start) in &outcome.appended {
+                    let records = &mut self.files.get_mut(path).unwrap().records[*start..];
+                    if self.dedup {
+                        mark_duplicates(&mut self.seen_keys, records);
+                    }
+                    for record in records.iter() {
+                        aggregates.add(record);
+                    }
+                }
+            }
+            _ => {
+                self.rebuild_aggregates(&mut outcome.reflagged);
+                outcome.rebuild = true;
+            }
+        }
+    }
+
+    /// Rebuilds the aggregates from every cached record. Duplicates are
+    /// flagged sequentially first, so the earliest copy in path order is the
+    /// one counted; records whose flag changed are added to `reflagged`.
+    fn rebuild_aggregates(&mut self, reflagged: &mut Vec<(PathBuf, usize)>) {
+        let mut paths: Vec<PathBuf> = self.files.keys().cloned().collect();
+        paths.sort();
+        self.seen_keys.clear();
+        for path in paths {
+            let records = &mut self.files.get_mut(&path).unwrap().records;
+            let changed = if self.dedup {
+                mark_duplicates(&mut self.seen_keys, records)
+            } else {
+                // Flags restored from the index may predate disabling dedup.
+                clear_duplicates(records)
+            };
+            reflagged.extend(changed.into_iter().map(|seq| (path.clone(), seq)));
+        }
+        self.aggregates = Some(aggregate(
+            &self.ordered_records(),
+            &self.project_names,
+            self.tz,
+            &TimeWindow::default(),
+        ));
+    }
+}
+
+/// Aggregates `records` in `window` with days and months in `tz`. Records
+/// are split into fixed chunks aggregated in parallel, then merged in order
+/// so the result does not depend on thread scheduling.
+fn aggregate(
+    records: &[Arc<MessageRecord>],
+    project_names: &[String],
+    tz: Tz,
+    window: &TimeWindow,
+) -> UsageAggregates {
+    let partials: Vec<UsageAggregates> = records
+        .par_chunks(AGGREGATE_CHUNK_RECORDS)
+        .map(|chunk| {
+            let mut partial = UsageAggregates::new(project_names, tz);
+            for record in chunk {
+                if window.contains(record) {
+                    partial.add(record);
+                }
+            }
+            partial
+        })
+        .collect();
+
+    let mut aggregates = UsageAggregates::new(project_names, tz);
+    for partial in partials {
+        aggregates.merge(partial);
+    }
+    aggregates
+}
+
+/// Flags records whose dedup key was already seen, recording the new keys.
+/// Returns the positions of the records whose flag changed.
+fn mark_duplicates(
+    seen_keys: &mut HashSet<Box<str>>,
+    records: &mut [Arc<MessageRecord>],
+) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for (seq, record) in records.iter_mut().enumerate() {
+        let duplicate = match &record.dedup_key {
+            Some(key) => !seen_keys.insert(key.clone()),
+            None => false,
+        };
+        if record.duplicate != duplicate {
+            Arc::make_mut(record).duplicate = duplicate;
+            changed.push(seq);
+        }
+    }
+    changed
+}
+
+/// Unflags every record, returning the positions of those that were flagged.
+fn clear_duplicates(records: &mut [Arc<MessageRecord>]) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for (seq, record) in records.iter_mut().enumerate() {
+        if record.duplicate {
+            Arc::make_mut(record).duplicate = false;
+            changed.push(seq);
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::Path;
+
+    /// A projects directory in the temp directory, removed when dropped.
+    struct TempProjects(PathBuf);
+
+    impl TempProjects {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("rust-backend-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            TempProjects(root)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+
+        fn append(&self, file: &str, lines: &[String]) {
+            let path = self.0.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut out = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap();
+            for line in lines {
+                writeln!(out, "{}", line).unwrap();
+            }
+        }
+    }
+
+    impl Drop for TempProjects {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn line(message_id: &str, request_id: &str, output_tokens: u64) -> String {
+        format!(
+            concat!(
+                r#"{{"sessionId":"s1","requestId":"{}","timestamp":"2026-01-01T10:00:00Z","#,
+                r#""message":{{"id":"{}","model":"claude-sonnet-4-5","usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#
+            ),
+            request_id, message_id, output_tokens
+        )
+    }
+
+    fn refresh(cache: &UsageCache, projects: &TempProjects) -> Arc<UsageSnapshot> {
+        cache
+            .refresh(projects.path(), &PricingTable::builtin())
+            .unwrap()
+    }
+
+    fn output_tokens(snapshot: &UsageSnapshot) -> Vec<u64> {
+        snapshot
+            .records(false)
+            .iter()
+            .map(|record| record.metrics.as_ref().unwrap().output_tokens)
+            .collect()
+    }
+
+    #[test]
+    fn counts_a_repeated_response_once() {
+        let projects = TempProjects::new("dedup");
+        projects.append(
+            "-a/1.jsonl",
+            &[
+                line("m1", "r1", 5),
+                line("m1", "r1", 5),
+                line("m1", "r2", 7),
+            ],
+        );
+        // The same response copied into a later session file.
+        projects.append("-b/2.jsonl", &[line("m1", "r1", 5)]);
+
+        let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, true);
+        let snapshot = refresh(&cache, &projects);
+        let data = snapshot
+            .cached_data(Tz::UTC, &TimeWindow::default())
+            .unwrap();
+
+        assert_eq!(output_tokens(&snapshot), [5, 7]);
+        assert_eq!(snapshot.records(true).len(), 4);
+        assert_eq!(data.duplicates_dropped, 2);
+        assert_eq!(data.daily_usage[0].output_tokens, 12);
+        assert_eq!(data.model_usage[0].messages, 2);
+        let b = data.projects.iter().find(|p| p.name == "-b").unwrap();
+        assert_eq!(b.message_count, 0);
+    }
+
+    #[test]
+    fn keeps_every_record_without_dedup() {
+        let projects = TempProjects::new("no-dedup");
+        projects.append("-a/1.jsonl", &[line("m1", "r1", 5), line("m1", "r1", 5)]);
+
+        let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, false);
+        let snapshot = refresh(&cache, &projects);
+
+        assert_eq!(output_tokens(&snapshot), [5, 5]);
+    }
+
+    #[test]
+    fn appended_repeats_are_dropped_like_on_a_rebuild() {
+        let projects = TempProjects::new("dedup-append");
+        projects.append("-a/1.jsonl", &[line("m1", "r1", 5)]);
+        let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, true);
+        refresh(&cache, &projects);
+
+        projects.append("-a/1.jsonl", &[line("m1", "r1", 5), line("m2", "r3", 9)]);
+        let snapshot = refresh(&cache, &projects);
+        let incremental = snapshot
+            .cached_data(Tz::UTC, &TimeWindow::default())
+            .unwrap();
+
+        assert_eq!(output_tokens(&snapshot), [5, 9]);
+        assert_eq!(incremental.duplicates_dropped, 1);
+
+        let rebuilt = UsageCache::new(Duration::ZERO, None, Tz::UTC, true);
+        let rebuilt = refresh(&rebuilt, &projects);
+        let rebuilt = rebuilt
+            .cached_data(Tz::UTC, &TimeWindow::default())
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&incremental.daily_usage).unwrap(),
+            serde_json::to_value(&rebuilt.daily_usage).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&incremental.projects).unwrap(),
+            serde_json::to_value(&rebuilt.projects).unwrap()
+        );
+    }
+
+    #[test]
+    fn a_removed_copy_hands_the_count_to_the_next_one() {
+        let projects = TempProjects::new("dedup-remove");
+        projects.append("-a/1.jsonl", &[line("m1", "r1", 5)]);
+        projects.append("-b/2.jsonl", &[line("m1", "r1", 5)]);
+        let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, true);
+        refresh(&cache, &projects);
+
+        fs::remove_file(Path::new(projects.path()).join("-a/1.jsonl")).unwrap();
+        let snapshot = refresh(&cache, &projects);
+        let data = snapshot
+            .cached_data(Tz::UTC, &TimeWindow::default())
+            .unwrap();
+
+        assert_eq!(output_tokens(&snapshot), [5]);
+        assert_eq!(data.duplicates_dropped, 0);
+        let b = data.projects.iter().find(|p| p.name == "-b").unwrap();
+        assert_eq!(b.message_count, 1);
+    }
+
+    #[test]
+    fn an_unreadable_file_does_not_stop_the_rescan() {
+        let projects = TempProjects::new("unreadable");
+        projects.append("-a/1.jsonl", &[line("m1", "r1", 5)]);
+        // Listed as a session file, but reading it fails.
+        fs::create_dir_all(projects.0.join("-a/2.jsonl")).unwrap();
+        let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, true);
+        refresh(&cache, &projects);
+
+        projects.append("-a/1.jsonl", &[line("m2", "r2", 7)]);
+        projects.append("-b/3.jsonl", &[line("m3", "r3", 9)]);
+        let snapshot = refresh(&cache, &projects);
+
+        assert_eq!(output_tokens(&snapshot), [5, 7, 9]);
+    }
+}
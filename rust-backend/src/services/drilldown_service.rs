@@ -0,0 +1,116 @@
+use crate::models::{DailyUsage, MessageRecord, ModelUsage, MonthlyUsage, ProjectData};
+use crate::services::{
+    hourly_usage, summarize_sessions, AllProjectData, HourlyFilter, HourlyUsage, SessionSummary,
+    TimeWindow, UsageAggregates,
+};
+use chrono::{Months, NaiveDate};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Sessions listed in a drill-down, most expensive first.
+const TOP_SESSIONS: usize = 10;
+
+/// One day's totals broken down by project, model and hour.
+#[derive(Debug, Serialize)]
+pub struct DayDetail {
+    #[serde(flatten)]
+    pub day: DailyUsage,
+    pub messages: usize,
+    pub projects: Vec<ProjectData>,
+    pub models: Vec<ModelUsage>,
+    pub hourly: Vec<HourlyUsage>,
+    /// Sessions active that day, counting only their messages from that day.
+    #[serde(rename = "topSessions")]
+    pub top_sessions: Vec<SessionSummary>,
+}
+
+/// One month's totals broken down by project, model and day.
+#[derive(Debug, Serialize)]
+pub struct MonthDetail {
+    #[serde(flatten)]
+    pub month: MonthlyUsage,
+    pub projects: Vec<ProjectData>,
+    pub models: Vec<ModelUsage>,
+    pub daily: Vec<DailyUsage>,
+    /// Sessions active that month, counting only their messages from that
+    /// month.
+    #[serde(rename = "topSessions")]
+    pub top_sessions: Vec<SessionSummary>,
+}
+
+/// The records in `window` and their aggregates, listing only projects that
+/// have messages in it.
+fn aggregate_window<'a>(
+    records: &[&'a MessageRecord],
+    window: &TimeWindow,
+    tz: Tz,
+) -> (Vec<&'a MessageRecord>, AllProjectData) {
+    let records: Vec<&MessageRecord> = records
+        .iter()
+        .copied()
+        .filter(|record| window.contains(record))
+        .collect();
+    let project_names: Vec<String> = records
+        .iter()
+        .map(|record| &*record.project)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    let mut aggregates = UsageAggregates::new(&project_names, tz);
+    for record in &records {
+        aggregates.add(record);
+    }
+    (records, aggregates.to_data())
+}
+
+fn top_sessions(records: &[&MessageRecord]) -> Vec<SessionSummary> {
+    let mut sessions = summarize_sessions(records.iter().copied());
+    sessions.sort_by(|a, b| {
+        b.cost
+            .parse::<f64>()
+            .unwrap_or(0.0)
+            .total_cmp(&a.cost.parse::<f64>().unwrap_or(0.0))
+    });
+    sessions.truncate(TOP_SESSIONS);
+    sessions
+}
+
+/// `None` when no message with usage was sent on `date` in `tz`.
+pub fn day_detail(records: &[&MessageRecord], date: NaiveDate, tz: Tz) -> Option<DayDetail> {
+    let window = TimeWindow::dates(date, date, tz);
+    let (records, data) = aggregate_window(records, &window, tz);
+    let day = data.daily_usage.into_iter().next()?;
+
+    Some(DayDetail {
+        day,
+        messages: records.iter().filter(|r| r.metrics.is_some()).count(),
+        projects: data.projects,
+        models: data.model_usage,
+        hourly: hourly_usage(&records, &HourlyFilter::default(), tz).hourly_data,
+        top_sessions: top_sessions(&records),
+    })
+}
+
+/// `None` when no message with usage was sent in the month starting on
+/// `first_day` in `tz`.
+pub fn month_detail(
+    records: &[&MessageRecord],
+    first_day: NaiveDate,
+    tz: Tz,
+) -> Option<MonthDetail> {
+    let last_day = first_day.checked_add_months(Months::new(1))?.pred_opt()?;
+    let window = TimeWindow::dates(first_day, last_day, tz);
+    let (records, data) = aggregate_window(records, &window, tz);
+    let month = data.monthly_usage.into_iter().next()?;
+
+    Some(MonthDetail {
+        month,
+        projects: data.projects,
+        models: data.model_usage,
+        daily: data.daily_usage,
+        top_sessions: top_sessions(&records),
+    })
+}
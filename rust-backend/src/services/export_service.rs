@@ -0,0 +1,387 @@
+use crate::models::{DailyUsage, MessageRecord, ModelUsage, MonthlyUsage, ProjectData};
+use crate::services::{AllProjectData, TimeWindow, UsageSnapshot};
+use anyhow::{bail, Result};
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Deserialize;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Rows per Parquet row group. Each group is written out once full, so
+/// large exports never hold more than one group of encoded rows.
+const ROW_GROUP_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn write<'a, R: ExportRow + 'a>(
+        self,
+        rows: impl IntoIterator<Item = &'a R>,
+        out: impl Write + Send,
+    ) -> Result<()> {
+        match self {
+            ExportFormat::Csv => write_csv(rows, out),
+            ExportFormat::Parquet => write_parquet(rows, out),
+        }
+    }
+}
+
+/// How a column is stored: UTF-8 strings, INT64 or DOUBLE in Parquet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    /// Empty in CSV, null in Parquet.
+    OptionalText,
+    Int,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExportValue<'a> {
+    Text(Option<&'a str>),
+    Int(u64),
+    Float(f64),
+}
+
+/// A row of an export. Column names are snake_case and stay the same
+/// between releases; new columns are only ever appended.
+pub trait ExportRow {
+    /// Name of the Parquet schema.
+    const TABLE: &'static str;
+    const COLUMNS: &'static [(&'static str, ColumnType)];
+
+    /// One value per column, in the order of `COLUMNS`.
+    fn values(&self) -> Vec<ExportValue<'_>>;
+}
+
+impl ExportRow for DailyUsage {
+    const TABLE: &'static str = "daily";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("date", ColumnType::Text),
+        ("input_tokens", ColumnType::Int),
+        ("output_tokens", ColumnType::Int),
+        ("cached_tokens", ColumnType::Int),
+        ("total_tokens", ColumnType::Int),
+        ("cost", ColumnType::Float),
+        ("sessions", ColumnType::Int),
+        ("new_input_tokens", ColumnType::Int),
+        ("cache_creation_tokens", ColumnType::Int),
+        ("cache_read_tokens", ColumnType::Int),
+    ];
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.date)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for MonthlyUsage {
+    const TABLE: &'static str = "monthly";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("month", ColumnType::Text),
+        ("input_tokens", ColumnType::Int),
+        ("output_tokens", ColumnType::Int),
+        ("cached_tokens", ColumnType::Int),
+        ("total_tokens", ColumnType::Int),
+        ("cost", ColumnType::Float),
+        ("sessions", ColumnType::Int),
+        ("messages", ColumnType::Int),
+        ("new_input_tokens", ColumnType::Int),
+        ("cache_creation_tokens", ColumnType::Int),
+        ("cache_read_tokens", ColumnType::Int),
+    ];
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.month)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.messages as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for ModelUsage {
+    const TABLE: &'static str = "models";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("model", ColumnType::Text),
+        ("input_tokens", ColumnType::Int),
+        ("output_tokens", ColumnType::Int),
+        ("cached_tokens", ColumnType::Int),
+        ("total_tokens", ColumnType::Int),
+        ("cost", ColumnType::Float),
+        ("sessions", ColumnType::Int),
+        ("messages", ColumnType::Int),
+        ("new_input_tokens", ColumnType::Int),
+        ("cache_creation_tokens", ColumnType::Int),
+        ("cache_read_tokens", ColumnType::Int),
+    ];
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.model)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.messages as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for ProjectData {
+    const TABLE: &'static str = "projects";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("project", ColumnType::Text),
+        ("total_tokens", ColumnType::Int),
+        ("cost", ColumnType::Float),
+        ("messages", ColumnType::Int),
+        ("last_activity", ColumnType::OptionalText),
+    ];
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.name)),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.total_cost_usd),
+            ExportValue::Int(self.message_count as u64),
+            ExportValue::Text(self.last_activity.as_deref()),
+        ]
+    }
+}
+
+impl ExportRow for MessageRecord {
+    const TABLE: &'static str = "messages";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("timestamp", ColumnType::Text),
+        ("project", ColumnType::Text),
+        ("session_id", ColumnType::OptionalText),
+        ("model", ColumnType::Text),
+        ("input_tokens", ColumnType::Int),
+        ("output_tokens", ColumnType::Int),
+        ("cached_tokens", ColumnType::Int),
+        ("total_tokens", ColumnType::Int),
+        ("cost", ColumnType::Float),
+        ("new_input_tokens", ColumnType::Int),
+        ("cache_creation_tokens", ColumnType::Int),
+        ("cache_read_tokens", ColumnType::Int),
+    ];
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        let metrics = self.metrics.clone().unwrap_or_default();
+        vec![
+            ExportValue::Text(Some(&self.timestamp)),
+            ExportValue::Text(Some(&self.project)),
+            ExportValue::Text(self.session_id.as_deref()),
+            ExportValue::Text(Some(&self.model)),
+            ExportValue::Int(metrics.input_tokens),
+            ExportValue::Int(metrics.output_tokens),
+            ExportValue::Int(metrics.cached_tokens),
+            ExportValue::Int(metrics.total_tokens),
+            ExportValue::Float(metrics.cost),
+            ExportValue::Int(metrics.new_input_tokens),
+            ExportValue::Int(metrics.cache_creation_tokens),
+            ExportValue::Int(metrics.cache_read_tokens),
+        ]
+    }
+}
+
+/// Which records the messages export keeps.
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+    pub window: TimeWindow,
+    pub project: Option<String>,
+    pub model: Option<String>,
+}
+
+impl MessageFilter {
+    pub fn matches(&self, record: &MessageRecord) -> bool {
+        record.metrics.is_some()
+            && self.window.contains(record)
+            && self
+                .project
+                .as_deref()
+                .is_none_or(|project| *record.project == *project)
+            && self
+                .model
+                .as_deref()
+                .is_none_or(|model| *record.model == *model)
+    }
+}
+
+/// The rows behind one export, taken from the cache before encoding starts
+/// so that a slow download does not hold it.
+pub enum ExportRows {
+    Daily(Arc<AllProjectData>),
+    Monthly(Arc<AllProjectData>),
+    Models(Arc<AllProjectData>),
+    Projects(Arc<AllProjectData>),
+    /// Records with usage that pass the filter, oldest first. They are
+    /// picked out of the snapshot while encoding rather than copied ahead.
+    Messages(Arc<UsageSnapshot>, MessageFilter),
+}
+
+impl ExportRows {
+    pub fn write(&self, format: ExportFormat, out: impl Write + Send) -> Result<()> {
+        match self {
+            ExportRows::Daily(usage) => format.write(&usage.daily_usage, out),
+            ExportRows::Monthly(usage) => format.write(&usage.monthly_usage, out),
+            ExportRows::Models(usage) => format.write(&usage.model_usage, out),
+            ExportRows::Projects(usage) => format.write(&usage.projects, out),
+            ExportRows::Messages(snapshot, filter) => {
+                let mut records: Vec<&MessageRecord> = snapshot
+                    .records(false)
+                    .into_iter()
+                    .filter(|record| filter.matches(record))
+                    .collect();
+                records.sort_by_key(|record| record.sent_at);
+                format.write(records, out)
+            }
+        }
+    }
+}
+
+/// Writes `rows` as CSV with a header line.
+pub fn write_csv<'a, R: ExportRow + 'a>(
+    rows: impl IntoIterator<Item = &'a R>,
+    out: impl Write,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(R::COLUMNS.iter().map(|(name, _)| name))?;
+    for row in rows {
+        writer.write_record(row.values().iter().map(|value| match value {
+            ExportValue::Text(text) => text.unwrap_or_default().to_string(),
+            ExportValue::Int(n) => n.to_string(),
+            ExportValue::Float(x) => x.to_string(),
+        }))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn parquet_schema<R: ExportRow>() -> String {
+    let fields: String = R::COLUMNS
+        .iter()
+        .map(|(name, kind)| match kind {
+            ColumnType::Text => format!("REQUIRED BYTE_ARRAY {} (UTF8); ", name),
+            ColumnType::OptionalText => format!("OPTIONAL BYTE_ARRAY {} (UTF8); ", name),
+            ColumnType::Int => format!("REQUIRED INT64 {}; ", name),
+            ColumnType::Float => format!("REQUIRED DOUBLE {}; ", name),
+        })
+        .collect();
+    format!("message {} {{ {}}}", R::TABLE, fields)
+}
+
+/// Writes `rows` as a Snappy-compressed Parquet file, one row group per
+/// `ROW_GROUP_SIZE` rows.
+pub fn write_parquet<'a, R: ExportRow + 'a>(
+    rows: impl IntoIterator<Item = &'a R>,
+    out: impl Write + Send,
+) -> Result<()> {
+    let schema = Arc::new(parse_message_type(&parquet_schema::<R>())?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(out, schema, properties)?;
+
+    let mut rows = rows.into_iter().peekable();
+    while rows.peek().is_some() {
+        let values: Vec<Vec<ExportValue>> =
+            rows.by_ref().take(ROW_GROUP_SIZE).map(R::values).collect();
+        let mut group = writer.next_row_group()?;
+        for (index, (name, kind)) in R::COLUMNS.iter().enumerate() {
+            let Some(mut column) = group.next_column()? else {
+                bail!("Parquet schema has no column {}", name);
+            };
+            let cells = values.iter().map(|row| row[index]);
+            match kind {
+                ColumnType::Text | ColumnType::OptionalText => {
+                    let texts: Vec<Option<&str>> = cells
+                        .map(|value| match value {
+                            ExportValue::Text(text) => text,
+                            _ => None,
+                        })
+                        .collect();
+                    let data: Vec<ByteArray> = texts.iter().flatten().map(|&t| t.into()).collect();
+                    let levels: Vec<i16> = texts.iter().map(|t| t.is_some() as i16).collect();
+                    let levels = (*kind == ColumnType::OptionalText).then_some(&levels[..]);
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&data, levels, None)?;
+                }
+                ColumnType::Int => {
+                    let data: Vec<i64> = cells
+                        .map(|value| match value {
+                            ExportValue::Int(n) => n as i64,
+                            _ => 0,
+                        })
+                        .collect();
+                    column.typed::<Int64Type>().write_batch(&data, None, None)?;
+                }
+                ColumnType::Float => {
+                    let data: Vec<f64> = cells
+                        .map(|value| match value {
+                            ExportValue::Float(x) => x,
+                            _ => 0.0,
+                        })
+                        .collect();
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&data, None, None)?;
+                }
+            }
+            column.close()?;
+        }
+        group.close()?;
+    }
+
+    writer.close()?;
+    Ok(())
+}
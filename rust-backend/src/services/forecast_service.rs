@@ -0,0 +1,452 @@
+use crate::models::{DayData, MessageRecord};
+use crate::services::TimeWindow;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Complete days before today that forecasts are fitted on; four weeks
+/// give every weekday the same weight.
+pub const LOOKBACK_DAYS: u64 = 28;
+
+/// Standard normal quantile for a two-sided 90% band.
+const Z_90: f64 = 1.645;
+
+#[derive(Debug, Serialize)]
+pub struct CostForecast {
+    /// Spent so far this month.
+    pub actual: String,
+    /// Month total if each remaining day costs the average of the same
+    /// weekday over the lookback.
+    pub estimate: String,
+    /// Month total following the least-squares trend of the lookback.
+    pub linear: String,
+    /// 90% band around `estimate`, from how far days strayed from their
+    /// weekday average.
+    pub low: String,
+    pub high: String,
+    #[serde(rename = "previousMonth")]
+    pub previous_month: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenForecast {
+    pub actual: u64,
+    pub estimate: u64,
+    pub linear: u64,
+    pub low: u64,
+    pub high: u64,
+    #[serde(rename = "previousMonth")]
+    pub previous_month: u64,
+}
+
+/// Month-end projection for all usage, a project or a model.
+#[derive(Debug, Serialize)]
+pub struct ScopeForecast {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub cost: CostForecast,
+    pub tokens: TokenForecast,
+}
+
+/// Expected usage of a day from today to the end of the month. Today's
+/// values include what was already spent.
+#[derive(Debug, Serialize)]
+pub struct ProjectedDay {
+    pub date: String,
+    pub cost: String,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForecastReport {
+    /// `YYYY-MM` of the month being forecast.
+    pub month: String,
+    #[serde(rename = "asOf")]
+    pub as_of: DateTime<Utc>,
+    #[serde(rename = "daysInMonth")]
+    pub days_in_month: u32,
+    /// Days of the month passed, including the elapsed part of today.
+    #[serde(rename = "daysElapsed")]
+    pub days_elapsed: f64,
+    #[serde(rename = "lookbackDays")]
+    pub lookback_days: u64,
+    pub total: ScopeForecast,
+    #[serde(rename = "projectedDaily")]
+    pub projected_daily: Vec<ProjectedDay>,
+    /// Highest estimated cost first; scopes without usage in the month or
+    /// the lookback are left out.
+    pub projects: Vec<ScopeForecast>,
+    pub models: Vec<ScopeForecast>,
+}
+
+/// Calendar facts shared by every scope's forecast.
+struct Calendar {
+    today: NaiveDate,
+    month_start: NaiveDate,
+    month_end: NaiveDate,
+    previous_month_start: NaiveDate,
+    lookback_start: NaiveDate,
+    /// Share of today still to come.
+    today_remaining: f64,
+}
+
+impl Calendar {
+    fn new(now: DateTime<Utc>, tz: Tz) -> Self {
+        let today = now.with_timezone(&tz).date_naive();
+        let month_start = today.with_day(1).unwrap_or(today);
+        let today_window = TimeWindow::dates(today, today, tz);
+        let today_remaining = match (today_window.start, today_window.end) {
+            (Some(start), Some(end)) if end > start => {
+                let left = (end - now).num_seconds() as f64;
+                (left / (end - start).num_seconds() as f64).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        };
+        Calendar {
+            today,
+            month_start,
+            month_end: month_start + Months::new(1) - Days::new(1),
+            previous_month_start: month_start - Months::new(1),
+            lookback_start: today - Days::new(LOOKBACK_DAYS),
+            today_remaining,
+        }
+    }
+
+    /// Days still to come with the share of each that is left.
+    fn remaining_days(&self) -> impl Iterator<Item = (NaiveDate, f64)> + '_ {
+        self.today
+            .iter_days()
+            .take_while(|date| *date <= self.month_end)
+            .map(|date| {
+                let weight = if date == self.today {
+                    self.today_remaining
+                } else {
+                    1.0
+                };
+                (date, weight)
+            })
+    }
+}
+
+/// One metric's month-end projection, before formatting.
+struct Projection {
+    actual: f64,
+    estimate: f64,
+    linear: f64,
+    low: f64,
+    high: f64,
+    previous_month: f64,
+    /// Expected value of each remaining day, without actual usage.
+    daily: Vec<(NaiveDate, f64)>,
+}
+
+impl Projection {
+    fn new(
+        days: &BTreeMap<NaiveDate, DayData>,
+        calendar: &Calendar,
+        value: fn(&DayData) -> f64,
+    ) -> Self {
+        let sum = |from: NaiveDate, to: NaiveDate| -> f64 {
+            days.range(from..=to)
+                .fold(0.0, |total, (_, day)| total + value(day))
+        };
+        let actual = sum(calendar.month_start, calendar.today);
+        let previous_month = sum(
+            calendar.previous_month_start,
+            calendar.month_start - Days::new(1),
+        );
+
+        // Lookback days without usage count as zero.
+        let history: Vec<(NaiveDate, f64)> = calendar
+            .lookback_start
+            .iter_days()
+            .take_while(|date| *date < calendar.today)
+            .map(|date| (date, days.get(&date).map_or(0.0, value)))
+            .collect();
+
+        let mut weekday_totals = [(0.0, 0usize); 7];
+        for (date, y) in &history {
+            let slot = &mut weekday_totals[date.weekday().num_days_from_monday() as usize];
+            slot.0 += y;
+            slot.1 += 1;
+        }
+        let weekday_mean = |date: NaiveDate| {
+            let (total, count) = weekday_totals[date.weekday().num_days_from_monday() as usize];
+            total / count.max(1) as f64
+        };
+
+        // Least-squares line through the lookback, x counted in days.
+        let n = history.len() as f64;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = history.iter().map(|(_, y)| y).sum::<f64>() / n.max(1.0);
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (x, (_, y)) in history.iter().enumerate() {
+            covariance += (x as f64 - mean_x) * (y - mean_y);
+            variance += (x as f64 - mean_x).powi(2);
+        }
+        let slope = if variance > 0.0 {
+            covariance / variance
+        } else {
+            0.0
+        };
+        let trend = |date: NaiveDate| {
+            let x = (date - calendar.lookback_start).num_days() as f64;
+            (mean_y + slope * (x - mean_x)).max(0.0)
+        };
+
+        // Spread of days around their weekday average; seven means are
+        // fitted, so seven degrees of freedom are lost.
+        let squared: f64 = history
+            .iter()
+            .map(|(date, y)| (y - weekday_mean(*date)).powi(2))
+            .sum();
+        let sigma = (squared / (n - 7.0).max(1.0)).sqrt();
+
+        let mut estimate = actual;
+        let mut linear = actual;
+        let mut weights_squared = 0.0;
+        let mut daily = Vec::new();
+        for (date, weight) in calendar.remaining_days() {
+            let expected = weekday_mean(date);
+            estimate += expected * weight;
+            linear += trend(date) * weight;
+            weights_squared += weight * weight;
+            daily.push((date, expected * weight));
+        }
+        let margin = Z_90 * sigma * weights_squared.sqrt();
+
+        Projection {
+            actual,
+            estimate,
+            linear,
+            low: (estimate - margin).max(actual),
+            high: estimate + margin,
+            previous_month,
+            daily,
+        }
+    }
+
+    fn to_cost(&self) -> CostForecast {
+        CostForecast {
+            actual: format!("{:.4}", self.actual),
+            estimate: format!("{:.4}", self.estimate),
+            linear: format!("{:.4}", self.linear),
+            low: format!("{:.4}", self.low),
+            high: format!("{:.4}", self.high),
+            previous_month: format!("{:.4}", self.previous_month),
+        }
+    }
+
+    fn to_tokens(&self) -> TokenForecast {
+        TokenForecast {
+            actual: self.actual.round() as u64,
+            estimate: self.estimate.round() as u64,
+            linear: self.linear.round() as u64,
+            low: self.low.round() as u64,
+            high: self.high.round() as u64,
+            previous_month: self.previous_month.round() as u64,
+        }
+    }
+}
+
+type Series = BTreeMap<NaiveDate, DayData>;
+
+fn add_day(series: &mut Series, date: NaiveDate, record: &MessageRecord) {
+    if let Some(metrics) = &record.metrics {
+        series
+            .entry(date)
+            .or_insert_with(|| DayData::new(date.to_string()))
+            .add(metrics, record.session_id.as_ref());
+    }
+}
+
+fn scope_forecast(
+    name: Option<&str>,
+    series: &Series,
+    calendar: &Calendar,
+) -> (ScopeForecast, f64) {
+    let cost = Projection::new(series, calendar, |day| day.cost);
+    let tokens = Projection::new(series, calendar, |day| day.total_tokens as f64);
+    let forecast = ScopeForecast {
+        name: name.map(String::from),
+        cost: cost.to_cost(),
+        tokens: tokens.to_tokens(),
+    };
+    (forecast, cost.estimate)
+}
+
+fn ranked(scopes: HashMap<&str, Series>, calendar: &Calendar) -> Vec<ScopeForecast> {
+    let recent = calendar.lookback_start.min(calendar.month_start);
+    let mut forecasts: Vec<(ScopeForecast, f64)> = scopes
+        .iter()
+        .filter(|(_, series)| series.range(recent..).next().is_some())
+        .map(|(name, series)| scope_forecast(Some(name), series, calendar))
+        .collect();
+    forecasts.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+    forecasts
+        .into_iter()
+        .map(|(forecast, _)| forecast)
+        .collect()
+}
+
+/// Projects this month's cost and tokens at `now`, with days in `tz`, for
+/// all usage and per project and model. `records` must exclude duplicates.
+pub fn forecast(records: &[&MessageRecord], tz: Tz, now: DateTime<Utc>) -> ForecastReport {
+    let calendar = Calendar::new(now, tz);
+    let first = calendar.lookback_start.min(calendar.previous_month_start);
+    let window = TimeWindow::dates(first, calendar.today, tz);
+
+    let mut total = Series::new();
+    let mut projects: HashMap<&str, Series> = HashMap::new();
+    let mut models: HashMap<&str, Series> = HashMap::new();
+    for record in records {
+        let Some(sent_at) = record.sent_at else {
+            continue;
+        };
+        if !window.contains(record) {
+            continue;
+        }
+        let date = sent_at.with_timezone(&tz).date_naive();
+        add_day(&mut total, date, record);
+        add_day(projects.entry(&record.project).or_default(), date, record);
+        add_day(models.entry(&record.model).or_default(), date, record);
+    }
+
+    let cost = Projection::new(&total, &calendar, |day| day.cost);
+    let tokens = Projection::new(&total, &calendar, |day| day.total_tokens as f64);
+    let projected_daily = cost
+        .daily
+        .iter()
+        .zip(&tokens.daily)
+        .map(|((date, cost), (_, tokens))| {
+            let today = (*date == calendar.today).then(|| total.get(date)).flatten();
+            ProjectedDay {
+                date: date.to_string(),
+                cost: format!("{:.4}", cost + today.map_or(0.0, |day| day.cost)),
+                total_tokens: (tokens + today.map_or(0.0, |day| day.total_tokens as f64)).round()
+                    as u64,
+            }
+        })
+        .collect();
+
+    let elapsed_days = (calendar.today - calendar.month_start).num_days() as f64;
+    ForecastReport {
+        month: calendar.month_start.format("%Y-%m").to_string(),
+        as_of: now,
+        days_in_month: calendar.month_end.day(),
+        days_elapsed: elapsed_days + 1.0 - calendar.today_remaining,
+        lookback_days: LOOKBACK_DAYS,
+        total: ScopeForecast {
+            name: None,
+            cost: cost.to_cost(),
+            tokens: tokens.to_tokens(),
+        },
+        projected_daily,
+        projects: ranked(projects, &calendar),
+        models: ranked(models, &calendar),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{assert_close, utc};
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    /// Days from `from` up to `to` inclusive costing `cost(date)`.
+    fn series(from: &str, to: &str, cost: impl Fn(NaiveDate) -> f64) -> Series {
+        date(from)
+            .iter_days()
+            .take_while(|day| *day <= date(to))
+            .map(|day| {
+                let mut data = DayData::new(day.to_string());
+                data.cost = cost(day);
+                (day, data)
+            })
+            .collect()
+    }
+
+    // 2026-03-16 is a Monday; the lookback runs from 2026-02-16 to 03-15.
+    const MONDAY: &str = "2026-03-16T00:00:00Z";
+
+    #[test]
+    fn steady_usage_projects_the_same_rate_with_no_band() {
+        let calendar = Calendar::new(utc(MONDAY), Tz::UTC);
+        let days = series("2026-02-01", "2026-03-15", |_| 2.0);
+        let projection = Projection::new(&days, &calendar, |day| day.cost);
+
+        assert_close(projection.actual, 30.0);
+        assert_close(projection.previous_month, 56.0);
+        // Sixteen days to go, today included.
+        assert_close(projection.estimate, 62.0);
+        assert_close(projection.linear, 62.0);
+        assert_close(projection.low, 62.0);
+        assert_close(projection.high, 62.0);
+        assert_eq!(projection.daily.len(), 16);
+    }
+
+    #[test]
+    fn weekday_averages_shape_the_estimate() {
+        let calendar = Calendar::new(utc(MONDAY), Tz::UTC);
+        let days = series("2026-02-16", "2026-03-15", |day| {
+            if day.weekday() == chrono::Weekday::Mon {
+                7.0
+            } else {
+                0.0
+            }
+        });
+        let projection = Projection::new(&days, &calendar, |day| day.cost);
+
+        // Mondays the 2nd and 9th so far; the 16th, 23rd and 30th to come.
+        assert_close(projection.actual, 14.0);
+        assert_close(projection.estimate, 35.0);
+        assert_close(projection.daily[0].1, 7.0);
+        assert_close(projection.daily[1].1, 0.0);
+    }
+
+    #[test]
+    fn linear_follows_the_lookback_trend() {
+        let calendar = Calendar::new(utc(MONDAY), Tz::UTC);
+        let start = date("2026-02-16");
+        let days = series("2026-02-16", "2026-03-15", |day| {
+            (day - start).num_days() as f64
+        });
+        let projection = Projection::new(&days, &calendar, |day| day.cost);
+
+        // March 1st to 15th are days 13 to 27; the 16th to 31st are 28 to 43.
+        assert_close(projection.actual, 300.0);
+        assert_close(projection.linear, 300.0 + 568.0);
+    }
+
+    #[test]
+    fn the_band_widens_with_spread_and_today_counts_what_is_left() {
+        let calendar = Calendar::new(utc("2026-03-16T12:00:00Z"), Tz::UTC);
+        assert_close(calendar.today_remaining, 0.5);
+
+        // Alternate weeks cost 1 and 3 a day: every weekday averages 2 and
+        // every day strays from it by 1.
+        let start = date("2026-02-16");
+        let days = series("2026-02-16", "2026-03-15", |day| {
+            if (day - start).num_days() / 7 % 2 == 0 {
+                1.0
+            } else {
+                3.0
+            }
+        });
+        let projection = Projection::new(&days, &calendar, |day| day.cost);
+
+        // March 1st ends a week at 3, then come a week at 1 and one at 3.
+        let actual = 3.0 + 7.0 * 1.0 + 7.0 * 3.0;
+        assert_close(projection.actual, actual);
+        assert_close(projection.estimate, actual + 0.5 * 2.0 + 15.0 * 2.0);
+        let sigma = (28.0 / 21.0_f64).sqrt();
+        let margin = Z_90 * sigma * (0.25_f64 + 15.0).sqrt();
+        assert_close(projection.high, projection.estimate + margin);
+        assert_close(projection.low, projection.estimate - margin);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::models::{MessageRecord, UsageMetrics};
+use crate::services::TimeWindow;
+use chrono::{NaiveDate, Timelike};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Which messages an hourly report covers. Dates are inclusive and in the
+/// report's timezone.
+#[derive(Debug, Default)]
+pub struct HourlyFilter {
+    pub window: TimeWindow,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub project: Option<String>,
+}
+
+impl HourlyFilter {
+    fn matches(&self, record: &MessageRecord, date: NaiveDate) -> bool {
+        self.window.contains(record)
+            && self.start_date.is_none_or(|start| date >= start)
+            && self.end_date.is_none_or(|end| date <= end)
+            && self
+                .project
+                .as_deref()
+                .is_none_or(|project| *record.project == *project)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HourlyUsage {
+    pub hour: u32,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: u64,
+    #[serde(rename = "cachedTokens")]
+    pub cached_tokens: u64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub sessions: usize,
+    pub requests: usize,
+    #[serde(rename = "avgCostPerRequest")]
+    pub avg_cost_per_request: f64,
+    #[serde(rename = "avgTokensPerRequest")]
+    pub avg_tokens_per_request: f64,
+}
+
+/// Usage within one hour of one day.
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapCell {
+    pub date: String,
+    pub hour: u32,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: u64,
+    #[serde(rename = "cachedTokens")]
+    pub cached_tokens: u64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub requests: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeakCostHour {
+    pub hour: u32,
+    pub cost: f64,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeakTokenHour {
+    pub hour: u32,
+    pub tokens: u64,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeakRequestHour {
+    pub hour: u32,
+    pub requests: usize,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HourlyStatistics {
+    #[serde(rename = "totalCost")]
+    pub total_cost: f64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    #[serde(rename = "totalRequests")]
+    pub total_requests: usize,
+    #[serde(rename = "avgCostPerHour")]
+    pub avg_cost_per_hour: f64,
+    #[serde(rename = "avgTokensPerHour")]
+    pub avg_tokens_per_hour: f64,
+    #[serde(rename = "avgRequestsPerHour")]
+    pub avg_requests_per_hour: f64,
+    #[serde(rename = "peakCostHour")]
+    pub peak_cost_hour: PeakCostHour,
+    #[serde(rename = "peakTokenHour")]
+    pub peak_token_hour: PeakTokenHour,
+    #[serde(rename = "peakRequestHour")]
+    pub peak_request_hour: PeakRequestHour,
+    /// Cost between 06:00 and 12:00.
+    #[serde(rename = "morningUsage")]
+    pub morning_usage: f64,
+    /// Cost between 12:00 and 18:00.
+    #[serde(rename = "afternoonUsage")]
+    pub afternoon_usage: f64,
+    /// Cost between 18:00 and 24:00.
+    #[serde(rename = "eveningUsage")]
+    pub evening_usage: f64,
+    /// Cost between 00:00 and 06:00.
+    #[serde(rename = "nightUsage")]
+    pub night_usage: f64,
+}
+
+/// Usage by hour of day and by (date, hour), in the shape of the Node
+/// backend's hourly route (`src/routes/api/hourly.js`).
+#[derive(Debug, Serialize)]
+pub struct HourlyReport {
+    /// Always 24 entries, one per hour.
+    #[serde(rename = "hourlyData")]
+    pub hourly_data: Vec<HourlyUsage>,
+    /// Only (date, hour) cells with messages, sorted by date then hour.
+    #[serde(rename = "heatmapData")]
+    pub heatmap_data: Vec<HeatmapCell>,
+    /// `None` when no message matched.
+    pub statistics: Option<HourlyStatistics>,
+}
+
+#[derive(Default)]
+struct HourTotals {
+    input_tokens: u64,
+    output_tokens: u64,
+    cached_tokens: u64,
+    total_tokens: u64,
+    cost: f64,
+    requests: usize,
+    sessions: HashSet<Arc<str>>,
+}
+
+impl HourTotals {
+    fn add(&mut self, metrics: &UsageMetrics, session_id: Option<&Arc<str>>) {
+        self.input_tokens += metrics.input_tokens;
+        self.output_tokens += metrics.output_tokens;
+        self.cached_tokens += metrics.cached_tokens;
+        self.total_tokens += metrics.total_tokens;
+        self.cost += metrics.cost;
+        self.requests += 1;
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.clone());
+        }
+    }
+
+    fn to_usage(&self, hour: u32) -> HourlyUsage {
+        let requests = self.requests.max(1) as f64;
+        HourlyUsage {
+            hour,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: self.cost,
+            sessions: self.sessions.len(),
+            requests: self.requests,
+            avg_cost_per_request: self.cost / requests,
+            avg_tokens_per_request: self.total_tokens as f64 / requests,
+        }
+    }
+
+    fn to_cell(&self, date: NaiveDate, hour: u32) -> HeatmapCell {
+        HeatmapCell {
+            date: date.to_string(),
+            hour,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: self.cost,
+            requests: self.requests,
+        }
+    }
+}
+
+/// Buckets every message with usage that passes `filter` by its local date
+/// and hour in `tz`. Around DST changes the skipped hour stays empty and the
+/// repeated one collects both.
+pub fn hourly_usage(records: &[&MessageRecord], filter: &HourlyFilter, tz: Tz) -> HourlyReport {
+    let mut by_hour: Vec<HourTotals> = (0..24).map(|_| HourTotals::default()).collect();
+    let mut by_date_hour: BTreeMap<(NaiveDate, u32), HourTotals> = BTreeMap::new();
+
+    for record in records {
+        let Some(metrics) = &record.metrics else {
+            continue;
+        };
+        let Some(sent_at) = record.sent_at else {
+            continue;
+        };
+        let sent_at = sent_at.with_timezone(&tz);
+        let (date, hour) = (sent_at.date_naive(), sent_at.hour());
+        if !filter.matches(record, date) {
+            continue;
+        }
+
+        let session_id = record.session_id.as_ref();
+        by_hour[hour as usize].add(metrics, session_id);
+        by_date_hour
+            .entry((date, hour))
+            .or_default()
+            .add(metrics, session_id);
+    }
+
+    let hourly_data: Vec<HourlyUsage> = by_hour
+        .iter()
+        .zip(0..)
+        .map(|(totals, hour)| totals.to_usage(hour))
+        .collect();
+    let heatmap_data = by_date_hour
+        .iter()
+        .map(|((date, hour), totals)| totals.to_cell(*date, *hour))
+        .collect();
+    let statistics = hourly_statistics(&hourly_data);
+
+    HourlyReport {
+        hourly_data,
+        heatmap_data,
+        statistics,
+    }
+}
+
+fn hourly_statistics(hours: &[HourlyUsage]) -> Option<HourlyStatistics> {
+    let total_requests: usize = hours.iter().map(|h| h.requests).sum();
+    if total_requests == 0 {
+        return None;
+    }
+    let total_cost: f64 = hours.iter().map(|h| h.cost).sum();
+    let total_tokens: u64 = hours.iter().map(|h| h.total_tokens).sum();
+    let hour_count = hours.len() as f64;
+
+    // The earliest hour wins ties.
+    let peak_by = |key: &dyn Fn(&HourlyUsage) -> f64| {
+        hours
+            .iter()
+            .fold(&hours[0], |max, h| if key(h) > key(max) { h } else { max })
+    };
+    let percentage = |part: f64, whole: f64| {
+        if whole > 0.0 {
+            part / whole * 100.0
+        } else {
+            0.0
+        }
+    };
+    let cost_between = |from: u32, to: u32| -> f64 {
+        hours
+            .iter()
+            .filter(|h| h.hour >= from && h.hour < to)
+            .map(|h| h.cost)
+            .sum()
+    };
+
+    let peak_cost = peak_by(&|h| h.cost);
+    let peak_tokens = peak_by(&|h| h.total_tokens as f64);
+    let peak_requests = peak_by(&|h| h.requests as f64);
+
+    Some(HourlyStatistics {
+        total_cost,
+        total_tokens,
+        total_requests,
+        avg_cost_per_hour: total_cost / hour_count,
+        avg_tokens_per_hour: total_tokens as f64 / hour_count,
+        avg_requests_per_hour: total_requests as f64 / hour_count,
+        peak_cost_hour: PeakCostHour {
+            hour: peak_cost.hour,
+            cost: peak_cost.cost,
+            percentage: percentage(peak_cost.cost, total_cost),
+        },
+        peak_token_hour: PeakTokenHour {
+            hour: peak_tokens.hour,
+            tokens: peak_tokens.total_tokens,
+            percentage: percentage(peak_tokens.total_tokens as f64, total_tokens as f64),
+        },
+        peak_request_hour: PeakRequestHour {
+            hour: peak_requests.hour,
+            requests: peak_requests.requests,
+            percentage: percentage(peak_requests.requests as f64, total_requests as f64),
+        },
+        morning_usage: cost_between(6, 12),
+        afternoon_usage: cost_between(12, 18),
+        evening_usage: cost_between(18, 24),
+        night_usage: cost_between(0, 6),
+    })
+}
@@ -0,0 +1,273 @@
+use crate::models::MessageRecord;
+use crate::services::{parse_message_line, IngestMetrics, PricingTable};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Read position within an append-only session file.
+#[derive(Debug, Clone, Default)]
+pub struct FileCursor {
+    /// Bytes consumed so far; always at a line boundary.
+    pub offset: u64,
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    session: Option<Arc<str>>,
+}
+
+pub enum TailOutcome {
+    /// The file has not changed since the last read.
+    Unchanged,
+    /// New lines were appended and parsed.
+    Appended(Vec<MessageRecord>),
+    /// The file shrank or was rewritten; the cursor was reset and every
+    /// line re-read. Records previously taken from it are stale.
+    Rewritten(Vec<MessageRecord>),
+}
+
+impl FileCursor {
+    /// A cursor restored from a saved checkpoint.
+    pub fn resume(offset: u64, modified: Option<SystemTime>, len: u64) -> Self {
+        FileCursor {
+            offset,
+            modified,
+            len,
+            session: None,
+        }
+    }
+
+    pub fn is_current(&self, modified: Option<SystemTime>, len: u64) -> bool {
+        self.modified == modified && self.len == len
+    }
+
+    /// Reads whatever was appended to `path` since the previous call.
+    ///
+    /// Only complete lines are consumed: a trailing fragment that does not
+    /// yet parse as JSON is left in place until the writer finishes it.
+    /// Reads and malformed lines are counted in `metrics`.
+    pub fn read_appended(
+        &mut self,
+        path: &Path,
+        project: &Arc<str>,
+        pricing: &PricingTable,
+        metrics: &IngestMetrics,
+    ) -> Result<TailOutcome> {
+        let mut file = File::open(path).context(format!("Failed to read file: {:?}", path))?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+
+        if self.is_current(modified, len) {
+            return Ok(TailOutcome::Unchanged);
+        }
+
+        let rewritten = len < self.offset;
+        if rewritten {
+            *self = FileCursor::default();
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::with_capacity(len.saturating_sub(self.offset) as usize);
+        file.read_to_end(&mut buf)
+            .context(format!("Failed to read file: {:?}", path))?;
+        metrics.record_file_read(buf.len() as u64);
+
+        let mut records = Vec::new();
+        let mut consumed = 0;
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            let complete = line.ends_with(b"\n");
+            let text = String::from_utf8_lossy(line);
+            if !complete && serde_json::from_str::<serde_json::Value>(text.trim()).is_err() {
+                break;
+            }
+            consumed += line.len();
+            match parse_message_line(&text, project, &mut self.session, pricing) {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => {}
+                Err(_) => metrics.record_parse_failure(),
+            }
+        }
+
+        self.offset += consumed as u64;
+        self.modified = modified;
+        self.len = len;
+
+        Ok(if rewritten {
+            TailOutcome::Rewritten(records)
+        } else {
+            TailOutcome::Appended(records)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{render_metrics, UsageAggregates};
+    use chrono_tz::Tz;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// A session file in the temp directory, removed when dropped.
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "rust-backend-{}-{}.jsonl",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, "").unwrap();
+            TempLog(path)
+        }
+
+        fn append(&self, text: &str) {
+            let mut file = OpenOptions::new().append(true).open(&self.0).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        }
+
+        fn replace(&self, text: &str) {
+            fs::write(&self.0, text).unwrap();
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn line(n: u32) -> String {
+        format!(
+            concat!(
+                r#"{{"type":"assistant","sessionId":"s1","requestId":"r{n}","#,
+                r#""timestamp":"2026-01-0{n}T10:00:00Z","message":{{"id":"m{n}","#,
+                r#""model":"claude-sonnet-4-5","usage":{{"input_tokens":{n}0,"output_tokens":{n}}}}}}}"#,
+                "\n"
+            ),
+            n = n
+        )
+    }
+
+    fn read(cursor: &mut FileCursor, log: &TempLog) -> TailOutcome {
+        let project: Arc<str> = "project".into();
+        cursor
+            .read_appended(
+                &log.0,
+                &project,
+                &PricingTable::builtin(),
+                &IngestMetrics::default(),
+            )
+            .unwrap()
+    }
+
+    fn request_ids(records: &[MessageRecord]) -> Vec<&str> {
+        records
+            .iter()
+            .map(|record| record.dedup_key.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn reads_only_appended_lines() {
+        let log = TempLog::new("appended");
+        log.append(&(line(1) + &line(2)));
+        let mut cursor = FileCursor::default();
+
+        let TailOutcome::Appended(records) = read(&mut cursor, &log) else {
+            panic!("expected the first read to append");
+        };
+        assert_eq!(request_ids(&records), ["m1:r1", "m2:r2"]);
+        assert_eq!(records[1].metrics.as_ref().unwrap().input_tokens, 20);
+        assert_eq!(cursor.offset, (line(1).len() + line(2).len()) as u64);
+        assert!(matches!(read(&mut cursor, &log), TailOutcome::Unchanged));
+
+        log.append(&line(3));
+        let TailOutcome::Appended(records) = read(&mut cursor, &log) else {
+            panic!("expected the appended line to be read");
+        };
+        assert_eq!(request_ids(&records), ["m3:r3"]);
+        assert_eq!(records[0].session_id.as_deref(), Some("s1"));
+    }
+
+    #[test]
+    fn leaves_an_unfinished_line_for_the_next_read() {
+        let log = TempLog::new("partial");
+        let second = line(2);
+        let (head, tail) = second.split_at(40);
+        log.append(&(line(1) + head));
+        let mut cursor = FileCursor::default();
+
+        let TailOutcome::Appended(records) = read(&mut cursor, &log) else {
+            panic!("expected the complete line to be read");
+        };
+        assert_eq!(request_ids(&records), ["m1:r1"]);
+        assert_eq!(cursor.offset, line(1).len() as u64);
+
+        log.append(tail);
+        let TailOutcome::Appended(records) = read(&mut cursor, &log) else {
+            panic!("expected the finished line to be read");
+        };
+        assert_eq!(request_ids(&records), ["m2:r2"]);
+    }
+
+    #[test]
+    fn rereads_a_truncated_file_from_the_start() {
+        let log = TempLog::new("truncated");
+        log.append(&(line(1) + &line(2)));
+        let mut cursor = FileCursor::default();
+        read(&mut cursor, &log);
+
+        log.replace(&line(3));
+        let TailOutcome::Rewritten(records) = read(&mut cursor, &log) else {
+            panic!("expected a shrunken file to be re-read");
+        };
+        assert_eq!(request_ids(&records), ["m3:r3"]);
+        assert_eq!(cursor.offset, line(3).len() as u64);
+    }
+
+    #[test]
+    fn resumes_from_a_saved_checkpoint() {
+        let log = TempLog::new("resume");
+        log.append(&(line(1) + &line(2)));
+        let mut cursor = FileCursor::resume(line(1).len() as u64, None, 0);
+
+        let TailOutcome::Appended(records) = read(&mut cursor, &log) else {
+            panic!("expected reading to resume after the checkpoint");
+        };
+        assert_eq!(request_ids(&records), ["m2:r2"]);
+    }
+
+    #[test]
+    fn counts_only_lines_that_are_not_json_as_parse_failures() {
+        let log = TempLog::new("failures");
+        log.append(&line(1));
+        log.append("\n");
+        log.append("{\"type\":\"summary\",\"summary\":\"Fixing the build\"}\n");
+        log.append("{\"message\":\"not an object\"}\n");
+        log.append("{\"sessionId\":\"s1\",\"message\":{\n");
+        log.append(&line(2));
+
+        let project: Arc<str> = "project".into();
+        let metrics = IngestMetrics::default();
+        let TailOutcome::Appended(records) = FileCursor::default()
+            .read_appended(&log.0, &project, &PricingTable::builtin(), &metrics)
+            .unwrap()
+        else {
+            panic!("expected the first read to append");
+        };
+        assert_eq!(request_ids(&records), ["m1:r1", "m2:r2"]);
+
+        let data = UsageAggregates::new(&[], Tz::UTC).to_data();
+        let rendered = render_metrics(&data, &metrics);
+        assert!(
+            rendered.contains("\nclaude_usage_parse_failures_total 1\n"),
+            "{}",
+            rendered
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use crate::services::parse_log_values;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Largest JSON-array log read whole; line-delimited logs are paged
+/// without a limit.
+const MAX_ARRAY_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("File access not allowed")]
+    Forbidden,
+    #[error("The requested file does not exist")]
+    NotFound,
+    #[error("File size exceeds {} MB limit", MAX_ARRAY_LOG_BYTES / 1024 / 1024)]
+    TooLarge,
+    #[error("Unable to read the requested file: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Which part of a log to return.
+#[derive(Debug, Default)]
+pub struct LogQuery {
+    /// Byte offset to resume from, as returned in `next_offset`. An offset
+    /// inside a line skips to the start of the next one.
+    pub offset: Option<u64>,
+    /// Entry index to start at, counted from the start of the file.
+    pub line: Option<usize>,
+    /// Most entries to return.
+    pub limit: usize,
+    /// Only entries whose `message.role` is this.
+    pub role: Option<String>,
+    /// Only entries whose `type` is this; MCP entries without one count as
+    /// `debug` or `error` by the field they carry.
+    pub kind: Option<String>,
+}
+
+impl LogQuery {
+    fn is_filtered(&self) -> bool {
+        self.role.is_some() || self.kind.is_some()
+    }
+
+    fn matches(&self, entry: &Value) -> bool {
+        let role = entry
+            .get("message")
+            .and_then(|message| message.get("role"))
+            .and_then(Value::as_str);
+        let kind = entry.get("type").and_then(Value::as_str).or_else(|| {
+            if entry.get("error").is_some() {
+                Some("error")
+            } else if entry.get("debug").is_some() {
+                Some("debug")
+            } else {
+                None
+            }
+        });
+        self.role
+            .as_deref()
+            .is_none_or(|wanted| role == Some(wanted))
+            && self
+                .kind
+                .as_deref()
+                .is_none_or(|wanted| kind == Some(wanted))
+    }
+}
+
+/// Where a page of log entries ended. Lines that are not JSON are passed
+/// on as strings and are left out whenever a filter is set.
+#[derive(Debug, Default)]
+pub struct PageEnd {
+    /// Whether every entry on the page parsed as JSON.
+    pub parsed: bool,
+    /// Byte offset to request the next page from; `None` for JSON-array
+    /// logs, which are paged by entry.
+    pub next_offset: Option<u64>,
+    /// Entry index of the first entry on the page; `None` when paging by a
+    /// byte offset, which does not tell how many lines came before.
+    pub start_line: Option<usize>,
+    pub next_line: Option<usize>,
+    pub has_more: bool,
+}
+
+/// A log opened for reading, already checked against the query it will be
+/// read with so that errors surface before any entry is sent.
+#[derive(Debug)]
+pub struct LogFile {
+    file: File,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    is_array: bool,
+}
+
+/// Resolves `file` to a canonical path inside one of `roots`, following
+/// symlinks so none can point outside them. Paths that are relative or
+/// contain `..` are refused outright.
+pub fn resolve_log_path(file: &str, roots: &[&str]) -> Result<PathBuf, LogError> {
+    let requested = Path::new(file);
+    if !requested.is_absolute() {
+        return Err(LogError::InvalidRequest(
+            "File path must be absolute".to_string(),
+        ));
+    }
+    if requested
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(LogError::Forbidden);
+    }
+
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .collect();
+    match fs::canonicalize(requested) {
+        Ok(path) if roots.iter().any(|root| path.starts_with(root)) => {
+            if !path.is_file() {
+                return Err(LogError::InvalidRequest("Not a file".to_string()));
+            }
+            Ok(path)
+        }
+        Ok(_) => Err(LogError::Forbidden),
+        // Only say a file is missing when it would have been allowed.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if roots.iter().any(|root| requested.starts_with(root)) {
+                Err(LogError::NotFound)
+            } else {
+                Err(LogError::Forbidden)
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl LogFile {
+    /// Opens `path` to be read with `query`.
+    pub fn open(path: &Path, query: &LogQuery) -> Result<Self, LogError> {
+        if query.offset.is_some() && query.line.is_some() {
+            return Err(LogError::InvalidRequest(
+                "Use either `offset` or `line`, not both".to_string(),
+            ));
+        }
+
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let mut first = [0u8; 64];
+        let read = file.read(&mut first)?;
+        let is_array = first[..read]
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'[');
+
+        if is_array {
+            if query.offset.is_some() {
+                return Err(LogError::InvalidRequest(
+                    "JSON-array logs are paged by `line`, not `offset`".to_string(),
+                ));
+            }
+            if size > MAX_ARRAY_LOG_BYTES {
+                return Err(LogError::TooLarge);
+            }
+        }
+
+        Ok(LogFile {
+            file,
+            size,
+            modified,
+            is_array,
+        })
+    }
+
+    /// Passes each entry of the page `query` asks for to `emit` as soon as
+    /// it is read. JSON-array logs (MCP) are parsed whole and paged by
+    /// entry; line-delimited logs are read from the requested offset until
+    /// `limit` entries match.
+    pub fn read_page(
+        mut self,
+        query: &LogQuery,
+        emit: impl FnMut(Value) -> io::Result<()>,
+    ) -> Result<PageEnd, LogError> {
+        if self.is_array {
+            let mut content = String::new();
+            self.file.seek(SeekFrom::Start(0))?;
+            self.file.read_to_string(&mut content)?;
+            array_page(&content, query, emit)
+        } else {
+            lines_page(self.file, self.size, query, emit)
+        }
+    }
+}
+
+fn array_page(
+    content: &str,
+    query: &LogQuery,
+    mut emit: impl FnMut(Value) -> io::Result<()>,
+) -> Result<PageEnd, LogError> {
+    let start = query.line.unwrap_or(0);
+    let mut emitted = 0;
+    let mut next_line = None;
+    for (index, entry) in parse_log_values(content)
+        .into_iter()
+        .enumerate()
+        .skip(start)
+    {
+        if !query.matches(&entry) {
+            continue;
+        }
+        if emitted == query.limit {
+            next_line = Some(index);
+            break;
+        }
+        emit(entry)?;
+        emitted += 1;
+    }
+
+    Ok(PageEnd {
+        parsed: true,
+        start_line: Some(start),
+        has_more: next_line.is_some(),
+        next_line,
+        ..PageEnd::default()
+    })
+}
+
+fn lines_page(
+    mut file: File,
+    len: u64,
+    query: &LogQuery,
+    mut emit: impl FnMut(Value) -> io::Result<()>,
+) -> Result<PageEnd, LogError> {
+    let offset = query.offset.unwrap_or(0).min(len);
+    // Land on a line boundary: an offset just past a newline already is.
+    let mut boundary = offset == 0;
+    if !boundary {
+        let mut previous = [0u8; 1];
+        file.seek(SeekFrom::Start(offset - 1))?;
+        file.read_exact(&mut previous)?;
+        boundary = previous[0] == b'\n';
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+    let mut position = offset;
+    let mut buf = Vec::new();
+    if !boundary {
+        position += reader.read_until(b'\n', &mut buf)? as u64;
+    }
+
+    // Line numbers are only known when reading from the start.
+    let mut line = (offset == 0).then_some(0);
+    if let Some(skip) = query.line {
+        for _ in 0..skip {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            position += read as u64;
+            line = line.map(|line| line + 1);
+        }
+    }
+    let start_line = line;
+
+    let mut emitted = 0;
+    let mut parsed = true;
+    let mut has_more = false;
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&buf);
+        // Comma-separated logs end each object with a comma.
+        let text = text.trim().trim_end_matches(',');
+        if !text.is_empty() {
+            let entry = serde_json::from_str::<Value>(text).ok();
+            // A session still being written may end in half a line; leave it
+            // for the next page.
+            if entry.is_none() && !buf.ends_with(b"\n") {
+                break;
+            }
+            let keep = match &entry {
+                Some(entry) => query.matches(entry),
+                None => !query.is_filtered(),
+            };
+            if keep {
+                if emitted == query.limit {
+                    has_more = true;
+                    break;
+                }
+                parsed &= entry.is_some();
+                emit(entry.unwrap_or_else(|| Value::String(text.to_string())))?;
+                emitted += 1;
+            }
+        }
+        position += read as u64;
+        line = line.map(|line| line + 1);
+    }
+
+    Ok(PageEnd {
+        parsed,
+        next_offset: Some(position),
+        start_line,
+        next_line: line,
+        has_more,
+    })
+}
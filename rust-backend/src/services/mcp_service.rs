@@ -0,0 +1,539 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Directory under each project's cache folder holding the IDE MCP logs.
+const MCP_LOG_DIR: &str = "mcp-logs-ide";
+
+/// Sessions returned with the tool statistics, most recent first.
+const MAX_SESSIONS: usize = 50;
+
+/// A single MCP log file.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpLogFile {
+    pub file: String,
+    #[serde(rename = "filePath")]
+    pub file_path: String,
+    pub project: String,
+    /// Last modification time of the file.
+    pub timestamp: Option<DateTime<Utc>>,
+    pub size: u64,
+    pub entries: usize,
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Calls to one tool within one project.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpToolProject {
+    pub project: String,
+    pub count: usize,
+    pub errors: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpToolStats {
+    pub name: String,
+    pub count: usize,
+    #[serde(rename = "sessionCount")]
+    pub session_count: usize,
+    #[serde(rename = "firstUsed")]
+    pub first_used: Option<String>,
+    #[serde(rename = "lastUsed")]
+    pub last_used: Option<String>,
+    /// Calls the log reports as failed.
+    pub errors: usize,
+    #[serde(rename = "errorRate")]
+    pub error_rate: f64,
+    /// `None` when no call of this tool logged how long it took.
+    #[serde(rename = "avgLatencyMs")]
+    pub avg_latency_ms: Option<f64>,
+    #[serde(rename = "maxLatencyMs")]
+    pub max_latency_ms: Option<f64>,
+    /// Most calls first.
+    pub projects: Vec<McpToolProject>,
+}
+
+/// Tool calls made in one session.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpSessionTools {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    #[serde(rename = "projectName")]
+    pub project_name: String,
+    /// Calls per tool name.
+    pub tools: BTreeMap<String, usize>,
+    pub errors: usize,
+    #[serde(rename = "startTime")]
+    pub start_time: Option<String>,
+    #[serde(rename = "endTime")]
+    pub end_time: Option<String>,
+    pub cwd: String,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpToolUsage {
+    #[serde(rename = "totalCalls")]
+    pub total_calls: usize,
+    #[serde(rename = "totalErrors")]
+    pub total_errors: usize,
+    #[serde(rename = "uniqueTools")]
+    pub unique_tools: usize,
+    /// Most called first.
+    pub tools: Vec<McpToolStats>,
+    /// Most recently started first, capped at [`MAX_SESSIONS`].
+    pub sessions: Vec<McpSessionTools>,
+}
+
+/// Everything read from the MCP logs in one pass.
+#[derive(Debug)]
+pub struct McpReport {
+    /// Most recently modified first.
+    pub logs: Vec<McpLogFile>,
+    pub tool_usage: McpToolUsage,
+    /// Every session with tool calls, most recently started first.
+    pub sessions: Vec<McpSessionTools>,
+}
+
+impl McpReport {
+    /// Sessions that called `tool`, most recently started first.
+    pub fn sessions_using<'a>(
+        &'a self,
+        tool: &'a str,
+    ) -> impl Iterator<Item = &'a McpSessionTools> {
+        self.sessions
+            .iter()
+            .filter(move |session| session.tools.contains_key(tool))
+    }
+}
+
+/// One line of an MCP log. Only the fields the statistics need are read.
+#[derive(Debug, Deserialize)]
+struct McpLogEntry {
+    debug: Option<String>,
+    error: Option<String>,
+    timestamp: Option<String>,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+    cwd: Option<String>,
+    method: Option<String>,
+    params: Option<Value>,
+}
+
+enum ToolEvent<'a> {
+    Call(&'a str),
+    Outcome {
+        name: &'a str,
+        failed: bool,
+        latency_ms: Option<f64>,
+    },
+}
+
+impl McpLogEntry {
+    fn tool_event(&self) -> Option<ToolEvent<'_>> {
+        if self.method.as_deref() == Some("tools/call") {
+            let name = self.params.as_ref()?.get("name")?.as_str()?;
+            return Some(ToolEvent::Call(name));
+        }
+        if let Some(debug) = &self.debug {
+            if let Some(name) = debug.strip_prefix("Calling MCP tool:") {
+                return Some(ToolEvent::Call(name.trim()));
+            }
+            if let Some((name, rest)) = quoted_tool(debug) {
+                if let Some(rest) = rest.strip_prefix("completed successfully in") {
+                    return Some(ToolEvent::outcome(name, false, rest));
+                }
+                if let Some(rest) = rest.strip_prefix("failed") {
+                    return Some(ToolEvent::outcome(name, true, rest));
+                }
+            }
+        }
+        let (name, rest) = quoted_tool(self.error.as_deref()?)?;
+        let rest = rest.strip_prefix("failed")?;
+        Some(ToolEvent::outcome(name, true, rest))
+    }
+}
+
+impl<'a> ToolEvent<'a> {
+    /// `rest` is the message after the verb, e.g. `after 1.5s: timeout`.
+    fn outcome(name: &'a str, failed: bool, rest: &str) -> Self {
+        let rest = rest.trim_start();
+        ToolEvent::Outcome {
+            name,
+            failed,
+            latency_ms: parse_duration_ms(rest.strip_prefix("after").unwrap_or(rest)),
+        }
+    }
+}
+
+/// Splits `Tool 'name' rest` into the tool name and the trimmed rest.
+fn quoted_tool(message: &str) -> Option<(&str, &str)> {
+    let rest = message.strip_prefix("Tool '")?;
+    let (name, rest) = rest.split_once('\'')?;
+    Some((name, rest.trim_start()))
+}
+
+/// Reads a leading `12ms` or `1.5s` as milliseconds.
+fn parse_duration_ms(text: &str) -> Option<f64> {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let value: f64 = text[..end].parse().ok()?;
+    match &text[end..] {
+        unit if unit.starts_with("ms") => Some(value),
+        unit if unit.starts_with('s') => Some(value * 1000.0),
+        _ => None,
+    }
+}
+
+/// MCP logs have been written as a JSON array, as comma-separated objects,
+/// and as one object per line; all three are accepted.
+pub fn parse_log_values(content: &str) -> Vec<Value> {
+    match serde_json::from_str::<Value>(content) {
+        Ok(Value::Array(values)) => values
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(inner) => inner,
+                value => vec![value],
+            })
+            .collect(),
+        Ok(value) => vec![value],
+        Err(_) => match serde_json::from_str::<Vec<Value>>(&format!("[{}]", content.trim())) {
+            Ok(values) => values,
+            Err(_) => content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line.trim()).ok())
+                .collect(),
+        },
+    }
+}
+
+fn parse_entries(content: &str) -> Vec<McpLogEntry> {
+    parse_log_values(content)
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect()
+}
+
+#[derive(Default)]
+struct ToolTotals {
+    count: usize,
+    errors: usize,
+    sessions: HashSet<String>,
+    first_used: Option<String>,
+    last_used: Option<String>,
+    latency_total: f64,
+    latency_count: usize,
+    latency_max: Option<f64>,
+    projects: HashMap<String, (usize, usize)>,
+}
+
+impl ToolTotals {
+    fn to_stats(&self, name: &str) -> McpToolStats {
+        let mut projects: Vec<McpToolProject> = self
+            .projects
+            .iter()
+            .map(|(project, (count, errors))| McpToolProject {
+                project: project.clone(),
+                count: *count,
+                errors: *errors,
+            })
+            .collect();
+        projects.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.project.cmp(&b.project))
+        });
+
+        McpToolStats {
+            name: name.to_string(),
+            count: self.count,
+            session_count: self.sessions.len(),
+            first_used: self.first_used.clone(),
+            last_used: self.last_used.clone(),
+            errors: self.errors,
+            error_rate: self.errors as f64 / self.count.max(1) as f64,
+            avg_latency_ms: (self.latency_count > 0)
+                .then(|| self.latency_total / self.latency_count as f64),
+            max_latency_ms: self.latency_max,
+            projects,
+        }
+    }
+
+    fn record_outcome(&mut self, project: &str, failed: bool, latency_ms: Option<f64>) {
+        if failed {
+            self.errors += 1;
+            if let Some((_, errors)) = self.projects.get_mut(project) {
+                *errors += 1;
+            }
+        }
+        if let Some(latency) = latency_ms {
+            self.latency_total += latency;
+            self.latency_count += 1;
+            self.latency_max = Some(self.latency_max.map_or(latency, |max| max.max(latency)));
+        }
+    }
+}
+
+#[derive(Default)]
+struct McpAggregates {
+    logs: Vec<McpLogFile>,
+    tools: HashMap<String, ToolTotals>,
+    sessions: Vec<McpSessionTools>,
+}
+
+impl McpAggregates {
+    fn add_file(&mut self, path: &Path, project: &str) {
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_session = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut log = McpLogFile {
+            file: file.clone(),
+            file_path: path.display().to_string(),
+            project: project.to_string(),
+            timestamp: None,
+            size: 0,
+            entries: 0,
+            session_id: file_session.clone(),
+            error: None,
+        };
+
+        let content = match fs::metadata(path).and_then(|metadata| {
+            log.timestamp = metadata.modified().ok().map(DateTime::<Utc>::from);
+            log.size = metadata.len();
+            fs::read_to_string(path)
+        }) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!("Failed to read MCP log {:?}: {}", path, e);
+                log.error = Some(e.to_string());
+                self.logs.push(log);
+                return;
+            }
+        };
+
+        let entries = parse_entries(&content);
+        log.entries = entries.len();
+        if let Some(session_id) = entries.iter().find_map(|e| e.session_id.as_deref()) {
+            log.session_id = session_id.to_string();
+        }
+        self.logs.push(log);
+
+        let mut sessions: BTreeMap<String, McpSessionTools> = BTreeMap::new();
+        // Calls still waiting for an outcome, per tool, with their start time.
+        let mut pending: HashMap<String, VecDeque<Option<DateTime<Utc>>>> = HashMap::new();
+
+        for entry in &entries {
+            let session_id = entry.session_id.as_deref().unwrap_or(&file_session);
+            let session =
+                sessions
+                    .entry(session_id.to_string())
+                    .or_insert_with(|| McpSessionTools {
+                        session_id: session_id.to_string(),
+                        project_name: project.to_string(),
+                        tools: BTreeMap::new(),
+                        errors: 0,
+                        start_time: None,
+                        end_time: None,
+                        cwd: project.to_string(),
+                        file: file.clone(),
+                    });
+            if let Some(timestamp) = &entry.timestamp {
+                session.start_time.get_or_insert_with(|| timestamp.clone());
+                session.end_time = Some(timestamp.clone());
+            }
+            if let Some(cwd) = entry
+                .cwd
+                .as_deref()
+                .or_else(|| entry.params.as_ref()?.get("cwd")?.as_str())
+            {
+                session.cwd = cwd.to_string();
+            }
+
+            let sent_at = entry
+                .timestamp
+                .as_deref()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc));
+
+            match entry.tool_event() {
+                Some(ToolEvent::Call(name)) => {
+                    let totals = self.tools.entry(name.to_string()).or_default();
+                    totals.count += 1;
+                    totals.sessions.insert(session_id.to_string());
+                    if let Some(timestamp) = &entry.timestamp {
+                        if totals
+                            .first_used
+                            .as_ref()
+                            .is_none_or(|first| timestamp < first)
+                        {
+                            totals.first_used = Some(timestamp.clone());
+                        }
+                        if totals
+                            .last_used
+                            .as_ref()
+                            .is_none_or(|last| timestamp > last)
+                        {
+                            totals.last_used = Some(timestamp.clone());
+                        }
+                    }
+                    totals.projects.entry(project.to_string()).or_default().0 += 1;
+                    *session.tools.entry(name.to_string()).or_default() += 1;
+                    pending
+                        .entry(name.to_string())
+                        .or_default()
+                        .push_back(sent_at);
+                }
+                Some(ToolEvent::Outcome {
+                    name,
+                    failed,
+                    latency_ms,
+                }) => {
+                    // Outcomes only count against a call seen in this file.
+                    let Some(started) = pending.get_mut(name).and_then(|calls| calls.pop_front())
+                    else {
+                        continue;
+                    };
+                    let latency = latency_ms.or_else(|| {
+                        let elapsed = sent_at? - started?;
+                        Some(elapsed.num_milliseconds() as f64)
+                    });
+                    if let Some(totals) = self.tools.get_mut(name) {
+                        totals.record_outcome(project, failed, latency);
+                    }
+                    if failed {
+                        session.errors += 1;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        self.sessions.extend(
+            sessions
+                .into_values()
+                .filter(|session| !session.tools.is_empty()),
+        );
+    }
+
+    fn into_report(mut self) -> McpReport {
+        let mut tools: Vec<McpToolStats> = self
+            .tools
+            .iter()
+            .map(|(name, totals)| totals.to_stats(name))
+            .collect();
+        tools.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        self.logs.sort_by(|a, b| {
+            b.timestamp
+                .cmp(&a.timestamp)
+                .then_with(|| a.file.cmp(&b.file))
+        });
+        self.sessions
+            .sort_by(|a, b| b.start_time.cmp(&a.start_time));
+
+        let tool_usage = McpToolUsage {
+            total_calls: tools.iter().map(|t| t.count).sum(),
+            total_errors: tools.iter().map(|t| t.errors).sum(),
+            unique_tools: tools.len(),
+            tools,
+            sessions: self.sessions.iter().take(MAX_SESSIONS).cloned().collect(),
+        };
+
+        McpReport {
+            logs: self.logs,
+            tool_usage,
+            sessions: self.sessions,
+        }
+    }
+}
+
+/// Reads every `<cache>/<project>/mcp-logs-ide/*.txt` file. A missing cache
+/// directory yields an empty report; unreadable log files are listed with
+/// their error.
+pub fn read_mcp_logs(cache_path: &str) -> Result<McpReport> {
+    let mut aggregates = McpAggregates::default();
+    let root = Path::new(cache_path);
+    if !root.exists() {
+        return Ok(aggregates.into_report());
+    }
+
+    let mut files = Vec::new();
+    for project in fs::read_dir(root).context(format!("Failed to read directory: {:?}", root))? {
+        let project = project?;
+        let log_dir = project.path().join(MCP_LOG_DIR);
+        let Ok(entries) = fs::read_dir(&log_dir) else {
+            continue;
+        };
+        let project_name = project.file_name().to_string_lossy().into_owned();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "txt") {
+                files.push((path, project_name.clone()));
+            }
+        }
+    }
+    files.sort();
+
+    for (path, project) in &files {
+        aggregates.add_file(path, project);
+    }
+    Ok(aggregates.into_report())
+}
+
+/// Keeps the last MCP report for a TTL so repeated requests do not re-read
+/// every log file. Readers only take the published report; a re-read runs
+/// one at a time without blocking them.
+pub struct McpLogCache {
+    ttl: Duration,
+    report: RwLock<Option<(Instant, Arc<McpReport>)>>,
+    /// Held for the length of a re-read.
+    refresh: Mutex<()>,
+}
+
+impl McpLogCache {
+    pub fn new(ttl: Duration) -> Self {
+        McpLogCache {
+            ttl,
+            report: RwLock::new(None),
+            refresh: Mutex::new(()),
+        }
+    }
+
+    /// The published report, if it is younger than the TTL.
+    pub fn fresh(&self) -> Option<Arc<McpReport>> {
+        let report = self.report.read().unwrap_or_else(|e| e.into_inner());
+        report
+            .as_ref()
+            .filter(|(read_at, _)| read_at.elapsed() < self.ttl)
+            .map(|(_, report)| report.clone())
+    }
+
+    /// Re-reads the logs under `cache_path` unless a concurrent call already
+    /// did, and publishes the result. Blocks on file IO.
+    pub fn refresh(&self, cache_path: &str) -> Result<Arc<McpReport>> {
+        let _refreshing = self.refresh.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(report) = self.fresh() {
+            return Ok(report);
+        }
+        let report = Arc::new(read_mcp_logs(cache_path)?);
+        *self.report.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((Instant::now(), report.clone()));
+        Ok(report)
+    }
+}
@@ -0,0 +1,264 @@
+use crate::services::{AllProjectData, ProjectModelUsage};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds in seconds of the ingest duration histogram buckets.
+const DURATION_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+/// Counters the usage cache keeps about its own work, for `/metrics`.
+#[derive(Default)]
+pub struct IngestMetrics {
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    files_scanned: AtomicU64,
+    bytes_read: AtomicU64,
+    parse_failures: AtomicU64,
+    session_files: AtomicU64,
+    /// Rescans per duration bucket, not cumulative; the last one is `+Inf`.
+    scan_buckets: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    scan_micros: AtomicU64,
+}
+
+impl IngestMetrics {
+    /// A request for cached data; a miss means the TTL had run out and the
+    /// projects directory was rescanned first.
+    pub fn record_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A session file read because it changed.
+    pub fn record_file_read(&self, bytes: u64) {
+        self.files_scanned.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// A complete line that is not valid JSON.
+    pub fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A rescan of the projects directory, leaving `session_files` cached.
+    pub fn record_scan(&self, duration: Duration, session_files: usize) {
+        let seconds = duration.as_secs_f64();
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.scan_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.scan_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.session_files
+            .store(session_files as u64, Ordering::Relaxed);
+    }
+}
+
+/// Quotes a label value as the text exposition format requires.
+fn label(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Renders usage totals per project and model, from the same aggregates
+/// as the JSON API, and the cache's ingest metrics in the Prometheus text
+/// format. Usage totals are gauges: they are recomputed from the session
+/// logs and drop when files are removed or duplicates found.
+pub fn render_metrics(data: &AllProjectData, ingest: &IngestMetrics) -> String {
+    let mut out = String::new();
+    let series: Vec<(String, &ProjectModelUsage)> = data
+        .project_model_usage
+        .iter()
+        .map(|row| {
+            (
+                format!(
+                    "project={},model={}",
+                    label(&row.project),
+                    label(&row.usage.model)
+                ),
+                row,
+            )
+        })
+        .collect();
+
+    header(
+        &mut out,
+        "claude_usage_tokens",
+        "gauge",
+        "Tokens reported by messages, by token type.",
+    );
+    for (labels, row) in &series {
+        let usage = &row.usage;
+        for (kind, tokens) in [
+            ("input", usage.input_tokens),
+            ("output", usage.output_tokens),
+            ("cache_creation", usage.cache_creation_tokens),
+            ("cache_read", usage.cache_read_tokens),
+        ] {
+            let _ = writeln!(
+                out,
+                "claude_usage_tokens{{{},type=\"{}\"}} {}",
+                labels, kind, tokens
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "claude_usage_cost_usd",
+        "gauge",
+        "Estimated cost of messages in USD.",
+    );
+    for (labels, row) in &series {
+        let _ = writeln!(out, "claude_usage_cost_usd{{{}}} {}", labels, row.cost);
+    }
+
+    header(
+        &mut out,
+        "claude_usage_messages",
+        "gauge",
+        "Messages that reported token usage.",
+    );
+    for (labels, row) in &series {
+        let _ = writeln!(
+            out,
+            "claude_usage_messages{{{}}} {}",
+            labels, row.usage.messages
+        );
+    }
+
+    header(
+        &mut out,
+        "claude_usage_sessions",
+        "gauge",
+        "Sessions with a message that reported usage; a session using several models counts once per model.",
+    );
+    for (labels, row) in &series {
+        let _ = writeln!(
+            out,
+            "claude_usage_sessions{{{}}} {}",
+            labels, row.usage.sessions
+        );
+    }
+
+    header(
+        &mut out,
+        "claude_usage_distinct_sessions",
+        "gauge",
+        "Sessions with a message that reported usage, across all projects and models.",
+    );
+    let _ = writeln!(
+        out,
+        "claude_usage_distinct_sessions {}",
+        data.total_sessions
+    );
+
+    header(
+        &mut out,
+        "claude_usage_duplicates_dropped",
+        "gauge",
+        "Messages left out of the totals as repeats of an earlier one.",
+    );
+    let _ = writeln!(
+        out,
+        "claude_usage_duplicates_dropped {}",
+        data.duplicates_dropped
+    );
+
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+    header(
+        &mut out,
+        "claude_usage_ingest_duration_seconds",
+        "histogram",
+        "Time taken to rescan the projects directory and fold in changes.",
+    );
+    let mut cumulative = 0;
+    for (bound, count) in DURATION_BUCKETS.iter().zip(&ingest.scan_buckets) {
+        cumulative += load(count);
+        let _ = writeln!(
+            out,
+            "claude_usage_ingest_duration_seconds_bucket{{le=\"{}\"}} {}",
+            bound, cumulative
+        );
+    }
+    cumulative += load(&ingest.scan_buckets[DURATION_BUCKETS.len()]);
+    let _ = writeln!(
+        out,
+        "claude_usage_ingest_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        cumulative
+    );
+    let _ = writeln!(
+        out,
+        "claude_usage_ingest_duration_seconds_sum {}",
+        load(&ingest.scan_micros) as f64 / 1e6
+    );
+    let _ = writeln!(
+        out,
+        "claude_usage_ingest_duration_seconds_count {}",
+        cumulative
+    );
+
+    for (name, kind, help, value) in [
+        (
+            "claude_usage_files_scanned_total",
+            "counter",
+            "Session files read because they were new or changed.",
+            load(&ingest.files_scanned),
+        ),
+        (
+            "claude_usage_bytes_read_total",
+            "counter",
+            "Bytes read from session files.",
+            load(&ingest.bytes_read),
+        ),
+        (
+            "claude_usage_parse_failures_total",
+            "counter",
+            "Session file lines that were not valid JSON.",
+            load(&ingest.parse_failures),
+        ),
+        (
+            "claude_usage_session_files",
+            "gauge",
+            "Session files held in the cache.",
+            load(&ingest.session_files),
+        ),
+        (
+            "claude_usage_cache_hits_total",
+            "counter",
+            "Requests served from the cache without a rescan.",
+            load(&ingest.cache_hits),
+        ),
+        (
+            "claude_usage_cache_misses_total",
+            "counter",
+            "Requests that waited for a rescan of the projects directory.",
+            load(&ingest.cache_misses),
+        ),
+    ] {
+        header(&mut out, name, kind, help);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    out
+}
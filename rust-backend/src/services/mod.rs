@@ -1,3 +1,41 @@
+pub mod alert_service;
+pub mod anomaly_service;
+pub mod budget_service;
+pub mod cache_service;
+pub mod drilldown_service;
+pub mod export_service;
+pub mod forecast_service;
+pub mod hourly_service;
+pub mod ingest_service;
+pub mod log_service;
+pub mod mcp_service;
+pub mod metrics_service;
+pub mod pricing_service;
 pub mod project_service;
+pub mod session_service;
+pub mod store_service;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod todo_service;
+pub mod tool_service;
+pub mod watch_service;
 
+pub use alert_service::*;
+pub use anomaly_service::*;
+pub use budget_service::*;
+pub use cache_service::*;
+pub use drilldown_service::*;
+pub use export_service::*;
+pub use forecast_service::*;
+pub use hourly_service::*;
+pub use ingest_service::*;
+pub use log_service::*;
+pub use mcp_service::*;
+pub use metrics_service::*;
+pub use pricing_service::*;
 pub use project_service::*;
+pub use session_service::*;
+pub use store_service::*;
+pub use todo_service::*;
+pub use tool_service::*;
+pub use watch_service::*;
@@ -0,0 +1,454 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::models::Usage;
+
+/// Token rates for a single model, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+impl ModelPricing {
+    pub const fn new(input: f64, output: f64, cache_write: f64, cache_read: f64) -> Self {
+        ModelPricing {
+            input,
+            output,
+            cache_write,
+            cache_read,
+        }
+    }
+
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens.unwrap_or(0) as f64 * self.input
+            + usage.output_tokens.unwrap_or(0) as f64 * self.output
+            + usage.cache_creation_tokens.unwrap_or(0) as f64 * self.cache_write
+            + usage.cache_read_tokens.unwrap_or(0) as f64 * self.cache_read)
+            / 1_000_000.0
+    }
+}
+
+const OPUS_4_5: ModelPricing = ModelPricing::new(5.0, 25.0, 6.25, 0.50);
+const OPUS_4: ModelPricing = ModelPricing::new(15.0, 75.0, 18.75, 1.50);
+const SONNET: ModelPricing = ModelPricing::new(3.0, 15.0, 3.75, 0.30);
+const HAIKU_4_5: ModelPricing = ModelPricing::new(1.0, 5.0, 1.25, 0.10);
+const HAIKU_3_5: ModelPricing = ModelPricing::new(0.80, 4.0, 1.0, 0.08);
+const HAIKU_3: ModelPricing = ModelPricing::new(0.25, 1.25, 0.30, 0.03);
+
+/// Built-in rates keyed by model id without the date suffix.
+const BUILTIN_RATES: &[(&str, ModelPricing)] = &[
+    ("claude-opus-4-5", OPUS_4_5),
+    ("claude-opus-4-1", OPUS_4),
+    ("claude-opus-4", OPUS_4),
+    ("claude-3-opus", OPUS_4),
+    ("claude-sonnet-4-5", SONNET),
+    ("claude-sonnet-4", SONNET),
+    ("claude-3-7-sonnet", SONNET),
+    ("claude-3-5-sonnet", SONNET),
+    ("claude-3-sonnet", SONNET),
+    ("claude-haiku-4-5", HAIKU_4_5),
+    ("claude-3-5-haiku", HAIKU_3_5),
+    ("claude-3-haiku", HAIKU_3),
+];
+
+/// Rates used when only the model family can be recognised.
+const FAMILY_RATES: &[(&str, ModelPricing)] =
+    &[("opus", OPUS_4_5), ("sonnet", SONNET), ("haiku", HAIKU_4_5)];
+
+/// Rates used for models that cannot be identified at all (e.g. "unknown").
+const DEFAULT_RATES: ModelPricing = SONNET;
+
+/// Rates for one model id over time. Periods are sorted by `effective_from`;
+/// an undated period applies to everything before the first dated one.
+struct ModelRates {
+    id: String,
+    periods: Vec<RatePeriod>,
+}
+
+struct RatePeriod {
+    effective_from: Option<DateTime<Utc>>,
+    rates: ModelPricing,
+    from_file: bool,
+}
+
+impl ModelRates {
+    fn at(&self, timestamp: Option<DateTime<Utc>>) -> ModelPricing {
+        let current = match timestamp {
+            Some(ts) => self
+                .periods
+                .iter()
+                .rev()
+                .find(|p| p.effective_from.is_none_or(|from| from <= ts)),
+            None => self.periods.last(),
+        };
+        // Messages older than every dated period fall back to the earliest one.
+        current
+            .or(self.periods.first())
+            .map(|p| p.rates)
+            .unwrap_or(DEFAULT_RATES)
+    }
+}
+
+pub struct PricingTable {
+    models: Vec<ModelRates>,
+}
+
+impl PricingTable {
+    pub fn builtin() -> Self {
+        PricingTable {
+            models: BUILTIN_RATES
+                .iter()
+                .map(|(id, rates)| ModelRates {
+                    id: id.to_string(),
+                    periods: vec![RatePeriod {
+                        effective_from: None,
+                        rates: *rates,
+                        from_file: false,
+                    }],
+                })
+                .collect(),
+        }
+    }
+
+    /// Builds the table from the built-in rates, overlaid with the pricing
+    /// file when one is configured.
+    pub fn load(pricing_file: Option<&str>) -> Result<Self> {
+        let mut table = Self::builtin();
+        if let Some(path) = pricing_file {
+            PricingFile::read(Path::new(path))
+                .and_then(|file| table.apply(file))
+                .with_context(|| format!("Invalid pricing file {}", path))?;
+            tracing::info!("Loaded pricing file {}", path);
+        }
+        Ok(table)
+    }
+
+    fn apply(&mut self, file: PricingFile) -> Result<()> {
+        for (index, entry) in file.models.into_iter().enumerate() {
+            let label = format!("models[{}] ({})", index, entry.id);
+            let id = entry.id.trim().to_lowercase();
+            if id.is_empty() {
+                bail!("models[{}]: `id` must not be empty", index);
+            }
+
+            let rates = ModelPricing::new(
+                entry.input,
+                entry.output,
+                entry.cache_write,
+                entry.cache_read,
+            );
+            for (name, value) in [
+                ("input", rates.input),
+                ("output", rates.output),
+                ("cache_write", rates.cache_write),
+                ("cache_read", rates.cache_read),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    bail!(
+                        "{}: `{}` must be a non-negative number, got {}",
+                        label,
+                        name,
+                        value
+                    );
+                }
+            }
+
+            let effective_from = entry
+                .effective_from
+                .as_deref()
+                .map(parse_effective_from)
+                .transpose()
+                .with_context(|| format!("{}: invalid `effective_from`", label))?;
+
+            let index = match self.models.iter().position(|m| m.id == id) {
+                Some(index) => index,
+                None => {
+                    self.models.push(ModelRates {
+                        id: id.clone(),
+                        periods: Vec::new(),
+                    });
+                    self.models.len() - 1
+                }
+            };
+            let model = &mut self.models[index];
+
+            // A file entry overrides the built-in period with the same start.
+            match model
+                .periods
+                .iter_mut()
+                .find(|p| p.effective_from == effective_from)
+            {
+                Some(period) if period.from_file => {
+                    bail!(
+                        "{}: duplicate entry for effective_from {}",
+                        label,
+                        entry.effective_from.as_deref().unwrap_or("(none)")
+                    );
+                }
+                Some(period) => {
+                    period.rates = rates;
+                    period.from_file = true;
+                }
+                None => model.periods.push(RatePeriod {
+                    effective_from,
+                    rates,
+                    from_file: true,
+                }),
+            }
+            model.periods.sort_by_key(|p| p.effective_from);
+        }
+        Ok(())
+    }
+
+    /// Resolves the rates for a model id at the time a message was sent.
+    ///
+    /// Lookup order: exact id, longest known id that prefixes the model
+    /// (so `claude-opus-4-1-20250805` resolves to `claude-opus-4-1`),
+    /// model family keyword, and finally the default Sonnet rates.
+    pub fn rates_for(&self, model: &str, timestamp: Option<DateTime<Utc>>) -> ModelPricing {
+        let model = model.to_lowercase();
+
+        if let Some(rates) = self.models.iter().find(|m| m.id == model) {
+            return rates.at(timestamp);
+        }
+
+        let prefix_match = self
+            .models
+            .iter()
+            .filter(|m| model.starts_with(m.id.as_str()) && model[m.id.len()..].starts_with('-'))
+            .max_by_key(|m| m.id.len());
+        if let Some(rates) = prefix_match {
+            return rates.at(timestamp);
+        }
+
+        FAMILY_RATES
+            .iter()
+            .find(|(family, _)| model.contains(family))
+            .map(|(_, rates)| *rates)
+            .unwrap_or(DEFAULT_RATES)
+    }
+}
+
+/// On-disk pricing file, in TOML or JSON:
+///
+/// ```toml
+/// [[models]]
+/// id = "claude-opus-4-5"
+/// effective_from = "2025-11-24"
+/// input = 5.0
+/// output = 25.0
+/// cache_write = 6.25
+/// cache_read = 0.5
+/// ```
+///
+/// Rates are USD per million tokens. `effective_from` accepts a date or an
+/// RFC 3339 timestamp and may be omitted for rates that always applied.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricingFile {
+    #[serde(default)]
+    models: Vec<PricingFileEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricingFileEntry {
+    id: String,
+    effective_from: Option<String>,
+    input: f64,
+    output: f64,
+    cache_write: f64,
+    cache_read: f64,
+}
+
+impl PricingFile {
+    fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context("Failed to read file")?;
+        let is_json = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+
+        if is_json {
+            serde_json::from_str(&content).context("Failed to parse JSON")
+        } else {
+            toml::from_str(&content).context("Failed to parse TOML")
+        }
+    }
+}
+
+fn parse_effective_from(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .with_context(|| format!("expected YYYY-MM-DD or RFC 3339, got {:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> Option<DateTime<Utc>> {
+        Some(parse_effective_from(value).unwrap())
+    }
+
+    fn table_with(file: &str) -> Result<PricingTable> {
+        let mut table = PricingTable::builtin();
+        table.apply(toml::from_str(file)?)?;
+        Ok(table)
+    }
+
+    #[test]
+    fn resolves_exact_prefix_and_family_ids() {
+        let table = PricingTable::builtin();
+        assert_eq!(table.rates_for("claude-opus-4-5", None), OPUS_4_5);
+        assert_eq!(table.rates_for("claude-opus-4-1-20250805", None), OPUS_4);
+        assert_eq!(
+            table.rates_for("Claude-3-5-Haiku-20241022", None),
+            HAIKU_3_5
+        );
+        assert_eq!(table.rates_for("claude-haiku-9", None), HAIKU_4_5);
+        assert_eq!(table.rates_for("unknown", None), DEFAULT_RATES);
+    }
+
+    #[test]
+    fn prefix_match_requires_a_dash_boundary() {
+        let table = table_with(
+            r#"
+            [[models]]
+            id = "claude-opus-4"
+            input = 1.0
+            output = 1.0
+            cache_write = 1.0
+            cache_read = 1.0
+            "#,
+        )
+        .unwrap();
+        // `claude-opus-4-5` is its own entry, not a suffix of `claude-opus-4`.
+        assert_eq!(table.rates_for("claude-opus-4-5-20251101", None), OPUS_4_5);
+        assert_eq!(
+            table.rates_for("claude-opus-4-20250514", None),
+            ModelPricing::new(1.0, 1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn effective_dates_select_the_period_in_force() {
+        let table = table_with(
+            r#"
+            [[models]]
+            id = "claude-opus-4-1"
+            effective_from = "2026-01-01"
+            input = 12.0
+            output = 60.0
+            cache_write = 15.0
+            cache_read = 1.2
+
+            [[models]]
+            id = "claude-opus-4-1"
+            effective_from = "2026-03-01T12:00:00Z"
+            input = 10.0
+            output = 50.0
+            cache_write = 12.5
+            cache_read = 1.0
+            "#,
+        )
+        .unwrap();
+        let model = "claude-opus-4-1-20250805";
+        let january = ModelPricing::new(12.0, 60.0, 15.0, 1.2);
+        let march = ModelPricing::new(10.0, 50.0, 12.5, 1.0);
+
+        assert_eq!(table.rates_for(model, at("2025-12-31T23:59:59Z")), OPUS_4);
+        assert_eq!(table.rates_for(model, at("2026-01-01")), january);
+        assert_eq!(table.rates_for(model, at("2026-03-01T11:59:59Z")), january);
+        assert_eq!(table.rates_for(model, at("2026-03-01T12:00:00Z")), march);
+        // Messages without a valid timestamp get the latest rates.
+        assert_eq!(table.rates_for(model, None), march);
+    }
+
+    #[test]
+    fn dated_only_models_fall_back_to_their_earliest_period() {
+        let table = table_with(
+            r#"
+            [[models]]
+            id = "claude-next"
+            effective_from = "2026-06-01"
+            input = 2.0
+            output = 8.0
+            cache_write = 2.5
+            cache_read = 0.2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            table.rates_for("claude-next", at("2026-01-01")),
+            ModelPricing::new(2.0, 8.0, 2.5, 0.2)
+        );
+    }
+
+    #[test]
+    fn file_entries_override_builtin_rates() {
+        let table = table_with(
+            r#"
+            [[models]]
+            id = "Claude-Sonnet-4-5"
+            input = 2.0
+            output = 10.0
+            cache_write = 2.5
+            cache_read = 0.2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            table.rates_for("claude-sonnet-4-5-20250929", None),
+            ModelPricing::new(2.0, 10.0, 2.5, 0.2)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let entry = |id: &str, from: &str, input: &str| {
+            format!(
+                "[[models]]\nid = {:?}\n{}input = {}\noutput = 1.0\ncache_write = 1.0\ncache_read = 1.0\n",
+                id, from, input
+            )
+        };
+
+        let negative = table_with(&entry("claude-x", "", "-1.0")).err().unwrap();
+        assert!(format!("{:#}", negative).contains("`input` must be a non-negative number"));
+
+        let empty_id = table_with(&entry(" ", "", "1.0")).err().unwrap();
+        assert!(format!("{:#}", empty_id).contains("`id` must not be empty"));
+
+        let bad_date = table_with(&entry("claude-x", "effective_from = \"soon\"\n", "1.0"))
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", bad_date).contains("invalid `effective_from`"));
+
+        let twice = entry("claude-x", "", "1.0");
+        let duplicate = table_with(&format!("{}{}", twice, twice)).err().unwrap();
+        assert!(format!("{:#}", duplicate).contains("duplicate entry"));
+
+        assert!(table_with("[[models]]\nid = \"claude-x\"\nprice = 1.0\n").is_err());
+    }
+
+    #[test]
+    fn cost_is_per_million_tokens() {
+        let usage = Usage {
+            input_tokens: Some(1_000_000),
+            output_tokens: Some(2_000_000),
+            cache_creation_tokens: Some(1_000_000),
+            cache_read_tokens: None,
+        };
+        assert_eq!(SONNET.cost(&usage), 3.0 + 30.0 + 3.75);
+    }
+}
@@ -1,296 +1,870 @@
 use crate::models::*;
-use anyhow::{Context, Result};
+use crate::services::{summarize_sessions, PricingTable, SessionSummary};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-
-pub fn process_daily_usage(projects_path: &str) -> Result<Vec<DailyUsage>> {
-    let project_data = process_all_project_data(projects_path)?;
-    Ok(project_data.daily_usage)
-}
-
-pub fn process_monthly_usage(projects_path: &str) -> Result<Vec<MonthlyUsage>> {
-    let project_data = process_all_project_data(projects_path)?;
-    Ok(project_data.monthly_usage)
-}
-
-pub fn process_model_usage(projects_path: &str) -> Result<Vec<ModelUsage>> {
-    let project_data = process_all_project_data(projects_path)?;
-    Ok(project_data.model_usage)
-}
-
-pub fn process_projects(projects_path: &str) -> Result<Vec<ProjectData>> {
-    let project_data = process_all_project_data(projects_path)?;
-    Ok(project_data.projects)
-}
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct AllProjectData {
     pub daily_usage: Vec<DailyUsage>,
     pub monthly_usage: Vec<MonthlyUsage>,
     pub model_usage: Vec<ModelUsage>,
     pub projects: Vec<ProjectData>,
+    /// Each model's totals within each project, by project then model.
+    pub project_model_usage: Vec<ProjectModelUsage>,
+    /// Distinct sessions with at least one message that reported usage.
+    pub total_sessions: usize,
+    /// Cost of every counted message, unrounded.
+    pub total_cost: f64,
+    pub duplicates_dropped: usize,
+}
+
+/// A model's usage within one project.
+#[derive(Debug, Clone)]
+pub struct ProjectModelUsage {
+    pub project: String,
+    pub usage: ModelUsage,
+    /// `usage.cost` before it is rounded for the JSON API.
+    pub cost: f64,
 }
 
-fn process_all_project_data(projects_path: &str) -> Result<AllProjectData> {
-    let project_dirs: Vec<PathBuf> = fs::read_dir(projects_path)
+pub fn list_project_dirs(projects_path: &str) -> Result<Vec<PathBuf>> {
+    let mut project_dirs: Vec<PathBuf> = fs::read_dir(projects_path)
         .context("Failed to read projects directory")?
         .filter_map(|entry| entry.ok())
         .map(|entry| entry.path())
         .filter(|path| path.is_dir())
         .collect();
+    project_dirs.sort();
+    Ok(project_dirs)
+}
 
-    let mut usage_by_date: HashMap<String, DayData> = HashMap::new();
-    let mut usage_by_month: HashMap<String, MonthData> = HashMap::new();
-    let mut usage_by_model: HashMap<String, ModelData> = HashMap::new();
-    let mut projects: Vec<ProjectInternal> = Vec::new();
-
-    for project_dir in &project_dirs {
-        let project_name = project_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        let files: Vec<PathBuf> = fs::read_dir(project_dir)
-            .context(format!("Failed to read project directory: {:?}", project_dir))?
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter(|path| {
-                path.extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| ext == "jsonl")
-                    .unwrap_or(false)
-            })
-            .collect();
+pub fn list_session_files(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(project_dir)
+        .context(format!(
+            "Failed to read project directory: {:?}",
+            project_dir
+        ))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext == "jsonl")
+                .unwrap_or(false)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
 
-        let mut project_total_tokens = 0u64;
-        let mut project_total_cost = 0.0f64;
-        let mut project_message_count = 0usize;
-        let mut project_last_activity: Option<String> = None;
+pub fn project_name(project_dir: &Path) -> String {
+    project_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_string()
+}
 
-        for file in files {
-            let content = fs::read_to_string(&file)
-                .context(format!("Failed to read file: {:?}", file))?;
+/// Parses one JSONL line into a record. Blank lines, and JSON entries
+/// without a message or a timestamp (summaries, snapshots) or in another
+/// shape, yield `Ok(None)`; lines that are not JSON at all are an error.
+///
+/// `session` holds the last session id seen in the file so consecutive
+/// records can share one allocation.
+pub fn parse_message_line(
+    line: &str,
+    project: &Arc<str>,
+    session: &mut Option<Arc<str>>,
+    pricing: &PricingTable,
+) -> serde_json::Result<Option<MessageRecord>> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
 
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
+    let msg: Message = match serde_json::from_str(trimmed) {
+        Ok(msg) => msg,
+        Err(e) if e.is_data() => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let (Some(message_content), Some(timestamp)) = (msg.message, msg.timestamp) else {
+        return Ok(None);
+    };
+
+    let session_id = msg.session_id.map(|id| match session {
+        Some(current) if **current == *id => current.clone(),
+        _ => {
+            let id: Arc<str> = id.into();
+            *session = Some(id.clone());
+            id
+        }
+    });
 
-                let msg: Message = match serde_json::from_str(trimmed) {
-                    Ok(m) => m,
-                    Err(_) => continue,
-                };
-
-                if let (Some(message_content), Some(timestamp)) =
-                    (msg.message, msg.timestamp.as_ref())
-                {
-                    project_message_count += 1;
-
-                    // Update last activity
-                    if project_last_activity.is_none()
-                        || timestamp > project_last_activity.as_ref().unwrap()
-                    {
-                        project_last_activity = Some(timestamp.clone());
-                    }
-
-                    if let Some(usage) = message_content.usage {
-                        let metrics = UsageMetrics::from_usage(&usage);
-                        let model = message_content.model.unwrap_or_else(|| "unknown".to_string());
-
-                        project_total_tokens += metrics.total_tokens;
-                        project_total_cost += metrics.cost;
-
-                        // Daily data
-                        let date = timestamp.split('T').next().unwrap_or("").to_string();
-                        let day_data = usage_by_date.entry(date.clone()).or_insert(DayData {
-                            date: date.clone(),
-                            input_tokens: 0,
-                            output_tokens: 0,
-                            cached_tokens: 0,
-                            total_tokens: 0,
-                            cost: 0.0,
-                            sessions: HashSet::new(),
-                            new_input_tokens: 0,
-                            cache_creation_tokens: 0,
-                            cache_read_tokens: 0,
-                        });
-
-                        day_data.input_tokens += metrics.input_tokens;
-                        day_data.output_tokens += metrics.output_tokens;
-                        day_data.cached_tokens += metrics.cached_tokens;
-                        day_data.total_tokens += metrics.total_tokens;
-                        day_data.cost += metrics.cost;
-                        day_data.new_input_tokens += metrics.new_input_tokens;
-                        day_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        day_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            day_data.sessions.insert(session_id.clone());
-                        }
-
-                        // Monthly data
-                        let month = {
-                            let parts: Vec<&str> = date.split('-').collect();
-                            if parts.len() >= 2 {
-                                format!("{}-{}", parts[0], parts[1])
-                            } else {
-                                continue;
-                            }
-                        };
-
-                        let month_data =
-                            usage_by_month.entry(month.clone()).or_insert(MonthData {
-                                month: month.clone(),
-                                input_tokens: 0,
-                                output_tokens: 0,
-                                cached_tokens: 0,
-                                total_tokens: 0,
-                                cost: 0.0,
-                                sessions: HashSet::new(),
-                                messages: 0,
-                                new_input_tokens: 0,
-                                cache_creation_tokens: 0,
-                                cache_read_tokens: 0,
-                            });
-
-                        month_data.input_tokens += metrics.input_tokens;
-                        month_data.output_tokens += metrics.output_tokens;
-                        month_data.cached_tokens += metrics.cached_tokens;
-                        month_data.total_tokens += metrics.total_tokens;
-                        month_data.cost += metrics.cost;
-                        month_data.messages += 1;
-                        month_data.new_input_tokens += metrics.new_input_tokens;
-                        month_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        month_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            month_data.sessions.insert(session_id.clone());
-                        }
-
-                        // Model data
-                        let model_data =
-                            usage_by_model.entry(model.clone()).or_insert(ModelData {
-                                model: model.clone(),
-                                input_tokens: 0,
-                                output_tokens: 0,
-                                cached_tokens: 0,
-                                total_tokens: 0,
-                                cost: 0.0,
-                                sessions: HashSet::new(),
-                                messages: 0,
-                                new_input_tokens: 0,
-                                cache_creation_tokens: 0,
-                                cache_read_tokens: 0,
-                            });
-
-                        model_data.input_tokens += metrics.input_tokens;
-                        model_data.output_tokens += metrics.output_tokens;
-                        model_data.cached_tokens += metrics.cached_tokens;
-                        model_data.total_tokens += metrics.total_tokens;
-                        model_data.cost += metrics.cost;
-                        model_data.messages += 1;
-                        model_data.new_input_tokens += metrics.new_input_tokens;
-                        model_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        model_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            model_data.sessions.insert(session_id.clone());
-                        }
-                    }
-                }
+    let dedup_key = match (&message_content.id, &msg.request_id) {
+        (Some(message_id), Some(request_id)) => {
+            Some(format!("{}:{}", message_id, request_id).into_boxed_str())
+        }
+        _ => None,
+    };
+
+    let mut tool_uses = Vec::new();
+    let mut tool_results = Vec::new();
+    for block in message_content.content {
+        match (
+            block.kind.as_deref(),
+            block.id,
+            block.name,
+            block.tool_use_id,
+        ) {
+            (Some("tool_use"), Some(id), Some(name), _) => tool_uses.push(ToolUse {
+                id: id.into(),
+                name: name.into(),
+            }),
+            (Some("tool_result"), _, _, Some(tool_use_id)) => tool_results.push(ToolResult {
+                tool_use_id: tool_use_id.into(),
+                is_error: block.is_error.unwrap_or(false),
+            }),
+            _ => {}
+        }
+    }
+
+    let sent_at = DateTime::parse_from_rfc3339(&timestamp)
+        .ok()
+        .map(|ts| ts.with_timezone(&Utc));
+    let model = message_content
+        .model
+        .unwrap_or_else(|| "unknown".to_string());
+    let metrics = message_content
+        .usage
+        .map(|usage| UsageMetrics::from_usage(&usage, &pricing.rates_for(&model, sent_at)));
+
+    Ok(Some(MessageRecord {
+        project: project.clone(),
+        session_id,
+        timestamp,
+        sent_at,
+        model: model.into(),
+        metrics,
+        dedup_key,
+        duplicate: false,
+        tool_uses,
+        tool_results,
+    }))
+}
+
+/// Time range a message must fall in to be counted. `start` is inclusive and
+/// `end` exclusive; an unbounded window keeps every message.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeWindow {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// Parses `from`/`to` query values, each a date (`2025-07-01`) or an ISO
+    /// 8601 datetime. Dates and datetimes without an offset are read in `tz`.
+    /// A date `to` includes that whole day; a datetime `to` is exclusive.
+    pub fn parse(from: Option<&str>, to: Option<&str>, tz: Tz) -> Result<Self> {
+        let window = TimeWindow {
+            start: from.map(|from| parse_bound(from, tz, false)).transpose()?,
+            end: to.map(|to| parse_bound(to, tz, true)).transpose()?,
+        };
+        if let (Some(start), Some(end)) = (window.start, window.end) {
+            if start >= end {
+                bail!("`from` must be earlier than `to`");
             }
         }
+        Ok(window)
+    }
 
-        projects.push(ProjectInternal {
-            name: project_name,
-            total_tokens: project_total_tokens,
-            total_cost: project_total_cost,
-            message_count: project_message_count,
-            last_activity: project_last_activity,
-        });
+    /// The calendar days `first` through `last` in `tz`.
+    pub fn dates(first: NaiveDate, last: NaiveDate, tz: Tz) -> Self {
+        let midnight = |date: NaiveDate| local_to_utc(date.and_time(NaiveTime::MIN), tz);
+        TimeWindow {
+            start: midnight(first),
+            end: last.checked_add_days(Days::new(1)).and_then(midnight),
+        }
     }
 
-    // Convert to response types
-    let mut daily_usage: Vec<DailyUsage> = usage_by_date
-        .into_iter()
-        .map(|(_, day)| DailyUsage {
-            date: day.date,
-            input_tokens: day.input_tokens,
-            output_tokens: day.output_tokens,
-            cached_tokens: day.cached_tokens,
-            total_tokens: day.total_tokens,
-            cost: format!("{:.4}", day.cost),
-            sessions: day.sessions.len(),
-            new_input_tokens: day.new_input_tokens,
-            cache_creation_tokens: day.cache_creation_tokens,
-            cache_read_tokens: day.cache_read_tokens,
-        })
-        .collect();
+    pub fn is_unbounded(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
 
-    daily_usage.sort_by(|a, b| a.date.cmp(&b.date));
-
-    let mut monthly_usage: Vec<MonthlyUsage> = usage_by_month
-        .into_iter()
-        .map(|(_, month)| MonthlyUsage {
-            month: month.month,
-            input_tokens: month.input_tokens,
-            output_tokens: month.output_tokens,
-            cached_tokens: month.cached_tokens,
-            total_tokens: month.total_tokens,
-            cost: format!("{:.4}", month.cost),
-            sessions: month.sessions.len(),
-            messages: month.messages,
-            new_input_tokens: month.new_input_tokens,
-            cache_creation_tokens: month.cache_creation_tokens,
-            cache_read_tokens: month.cache_read_tokens,
-        })
-        .collect();
+    /// Records without a parseable timestamp only match an unbounded window.
+    pub fn contains(&self, record: &MessageRecord) -> bool {
+        self.contains_time(record.sent_at)
+    }
 
-    monthly_usage.sort_by(|a, b| a.month.cmp(&b.month));
-
-    let mut model_usage: Vec<ModelUsage> = usage_by_model
-        .into_iter()
-        .map(|(_, model)| ModelUsage {
-            model: model.model,
-            input_tokens: model.input_tokens,
-            output_tokens: model.output_tokens,
-            cached_tokens: model.cached_tokens,
-            total_tokens: model.total_tokens,
-            cost: format!("{:.4}", model.cost),
-            sessions: model.sessions.len(),
-            messages: model.messages,
-            new_input_tokens: model.new_input_tokens,
-            cache_creation_tokens: model.cache_creation_tokens,
-            cache_read_tokens: model.cache_read_tokens,
+    /// Like [`contains`](Self::contains), for a message's `sent_at`.
+    pub fn contains_time(&self, sent_at: Option<DateTime<Utc>>) -> bool {
+        if self.is_unbounded() {
+            return true;
+        }
+        sent_at.is_some_and(|sent_at| {
+            self.start.is_none_or(|start| sent_at >= start)
+                && self.end.is_none_or(|end| sent_at < end)
         })
-        .collect();
+    }
+}
 
-    model_usage.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
+fn parse_bound(value: &str, tz: Tz, is_end: bool) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    let naive = if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if is_end {
+            date.checked_add_days(Days::new(1))
+        } else {
+            Some(date)
+        };
+        date.map(|date| date.and_time(NaiveTime::MIN))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+            .ok()
+    };
+    let Some(naive) = naive else {
+        bail!(
+            "Invalid date {:?}: expected YYYY-MM-DD or an ISO 8601 datetime",
+            value
+        );
+    };
+
+    match local_to_utc(naive, tz) {
+        Some(instant) => Ok(instant),
+        None => bail!("Invalid date {:?}: not a valid local time", value),
+    }
+}
 
-    let mut project_data: Vec<ProjectData> = projects
-        .into_iter()
-        .map(|p| ProjectData {
-            name: p.name,
-            total_tokens: p.total_tokens,
-            total_cost: format!("{:.4}", p.total_cost),
-            message_count: p.message_count,
-            last_activity: p.last_activity,
+/// Ambiguous local times (DST fall-back) take the earlier instant; times
+/// skipped by a spring-forward gap are read an hour later.
+fn local_to_utc(naive: NaiveDateTime, tz: Tz) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + TimeDelta::hours(1)))
+                .earliest()
         })
+        .map(|local| local.with_timezone(&Utc))
+}
+
+/// New messages plus the refreshed totals of every bucket they changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageDelta {
+    pub messages: Vec<MessageUsage>,
+    pub daily: Vec<DailyUsage>,
+    pub models: Vec<ModelUsage>,
+    pub projects: Vec<ProjectData>,
+}
+
+/// Running daily, monthly, model and project totals. Records can be added
+/// one at a time, so appended log lines are merged without a full rebuild.
+/// Days and months are calendar dates in `tz`.
+pub struct UsageAggregates {
+    tz: Tz,
+    usage_by_date: HashMap<String, DayData>,
+    usage_by_month: HashMap<String, MonthData>,
+    usage_by_model: HashMap<String, ModelData>,
+    usage_by_project_model: HashMap<(Arc<str>, Arc<str>), ModelData>,
+    projects: HashMap<String, ProjectInternal>,
+    sessions: HashSet<Arc<str>>,
+    duplicates_dropped: usize,
+}
+
+impl UsageAggregates {
+    /// Every project in `project_names` is listed, even if it has no messages.
+    pub fn new(project_names: &[String], tz: Tz) -> Self {
+        UsageAggregates {
+            tz,
+            usage_by_date: HashMap::new(),
+            usage_by_month: HashMap::new(),
+            usage_by_model: HashMap::new(),
+            usage_by_project_model: HashMap::new(),
+            projects: project_names
+                .iter()
+                .map(|name| (name.clone(), ProjectInternal::new(name.clone())))
+                .collect(),
+            sessions: HashSet::new(),
+            duplicates_dropped: 0,
+        }
+    }
+
+    pub fn add(&mut self, record: &MessageRecord) {
+        if record.duplicate {
+            self.duplicates_dropped += 1;
+            return;
+        }
+
+        if let Some(project) = self.projects.get_mut(&*record.project) {
+            project.add(record);
+        }
+
+        let Some(metrics) = &record.metrics else {
+            return;
+        };
+        let session_id = record.session_id.as_ref();
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.clone());
+        }
+
+        // Daily data
+        let date = self.local_date(record);
+        let date = date.as_str();
+        self.usage_by_date
+            .entry(date.to_string())
+            .or_insert_with(|| DayData::new(date.to_string()))
+            .add(metrics, session_id);
+
+        // Monthly data
+        if let Some(month) = date.get(..7).filter(|m| m.as_bytes().get(4) == Some(&b'-')) {
+            self.usage_by_month
+                .entry(month.to_string())
+                .or_insert_with(|| MonthData::new(month.to_string()))
+                .add(metrics, session_id);
+        }
+
+        // Model data
+        self.usage_by_model
+            .entry(record.model.to_string())
+            .or_insert_with(|| ModelData::new(record.model.to_string()))
+            .add(metrics, session_id);
+        self.usage_by_project_model
+            .entry((record.project.clone(), record.model.clone()))
+            .or_insert_with(|| ModelData::new(record.model.to_string()))
+            .add(metrics, session_id);
+    }
+
+    /// Adds usage summed outside the cache: `messages` counted messages of
+    /// one session and model, all sent on the local day of `sent_at`. A
+    /// project's message count and last activity come from
+    /// [`add_activity`](Self::add_activity) instead.
+    pub fn add_summed(
+        &mut self,
+        project: &Arc<str>,
+        session_id: Option<&Arc<str>>,
+        model: &Arc<str>,
+        sent_at: DateTime<Utc>,
+        metrics: &UsageMetrics,
+        messages: usize,
+    ) {
+        if let Some(data) = self.projects.get_mut(&**project) {
+            data.total_tokens += metrics.total_tokens;
+            data.total_cost += metrics.cost;
+        }
+        if let Some(session_id) = session_id {
+            self.sessions.insert(session_id.clone());
+        }
+
+        let date = sent_at.with_timezone(&self.tz).date_naive().to_string();
+        self.usage_by_month
+            .entry(date[..7].to_string())
+            .or_insert_with(|| MonthData::new(date[..7].to_string()))
+            .add_messages(metrics, session_id, messages);
+        self.usage_by_date
+            .entry(date.clone())
+            .or_insert_with(|| DayData::new(date))
+            .add(metrics, session_id);
+
+        self.usage_by_model
+            .entry(model.to_string())
+            .or_insert_with(|| ModelData::new(model.to_string()))
+            .add_messages(metrics, session_id, messages);
+        self.usage_by_project_model
+            .entry((project.clone(), model.clone()))
+            .or_insert_with(|| ModelData::new(model.to_string()))
+            .add_messages(metrics, session_id, messages);
+    }
+
+    /// Adds `messages` counted messages of `project`, with or without usage,
+    /// the latest sent at `last_activity`.
+    pub fn add_activity(&mut self, project: &str, messages: usize, last_activity: String) {
+        if let Some(data) = self.projects.get_mut(project) {
+            data.message_count += messages;
+            if data
+                .last_activity
+                .as_ref()
+                .is_none_or(|last| last_activity > *last)
+            {
+                data.last_activity = Some(last_activity);
+            }
+        }
+    }
+
+    pub fn add_duplicates(&mut self, count: usize) {
+        self.duplicates_dropped += count;
+    }
+
+    /// The record's calendar day in `tz` as `YYYY-MM-DD`. Timestamps that do
+    /// not parse keep the date written in the log.
+    fn local_date(&self, record: &MessageRecord) -> String {
+        match record.sent_at {
+            Some(sent_at) => sent_at.with_timezone(&self.tz).date_naive().to_string(),
+            None => record.timestamp.split('T').next().unwrap_or("").to_string(),
+        }
+    }
+
+    /// Folds a partial aggregate built over a disjoint set of records into
+    /// this one.
+    pub fn merge(&mut self, other: UsageAggregates) {
+        self.duplicates_dropped += other.duplicates_dropped;
+        self.sessions.extend(other.sessions);
+        for (date, day) in other.usage_by_date {
+            match self.usage_by_date.get_mut(&date) {
+                Some(existing) => existing.merge(day),
+                None => {
+                    self.usage_by_date.insert(date, day);
+                }
+            }
+        }
+        for (month, data) in other.usage_by_month {
+            match self.usage_by_month.get_mut(&month) {
+                Some(existing) => existing.merge(data),
+                None => {
+                    self.usage_by_month.insert(month, data);
+                }
+            }
+        }
+        for (model, data) in other.usage_by_model {
+            match self.usage_by_model.get_mut(&model) {
+                Some(existing) => existing.merge(data),
+                None => {
+                    self.usage_by_model.insert(model, data);
+                }
+            }
+        }
+        for (key, data) in other.usage_by_project_model {
+            match self.usage_by_project_model.get_mut(&key) {
+                Some(existing) => existing.merge(data),
+                None => {
+                    self.usage_by_project_model.insert(key, data);
+                }
+            }
+        }
+        for (name, project) in other.projects {
+            match self.projects.get_mut(&name) {
+                Some(existing) => existing.merge(project),
+                None => {
+                    self.projects.insert(name, project);
+                }
+            }
+        }
+    }
+
+    /// Current totals for the buckets a set of new records touched, used to
+    /// describe an incremental update to live clients.
+    pub fn delta_for(&self, records: &[&MessageRecord]) -> UsageDelta {
+        let mut dates: Vec<String> = Vec::new();
+        let mut models: Vec<&str> = Vec::new();
+        let mut projects: Vec<&str> = Vec::new();
+        for record in records.iter().filter(|r| !r.duplicate) {
+            let date = self.local_date(record);
+            if record.metrics.is_some() && !dates.contains(&date) {
+                dates.push(date);
+            }
+            if record.metrics.is_some() && !models.contains(&&*record.model) {
+                models.push(&record.model);
+            }
+            if !projects.contains(&&*record.project) {
+                projects.push(&record.project);
+            }
+        }
+        dates.sort();
+
+        UsageDelta {
+            messages: records
+                .iter()
+                .filter(|r| !r.duplicate)
+                .filter_map(|r| r.to_usage())
+                .collect(),
+            daily: dates
+                .iter()
+                .filter_map(|date| self.usage_by_date.get(date))
+                .map(DayData::to_usage)
+                .collect(),
+            models: models
+                .iter()
+                .filter_map(|model| self.usage_by_model.get(*model))
+                .map(ModelData::to_usage)
+                .collect(),
+            projects: projects
+                .iter()
+                .filter_map(|project| self.projects.get(*project))
+                .map(ProjectInternal::to_data)
+                .collect(),
+        }
+    }
+
+    pub fn to_data(&self) -> AllProjectData {
+        // Convert to response types
+        let mut days: Vec<&DayData> = self.usage_by_date.values().collect();
+        days.sort_by(|a, b| a.date.cmp(&b.date));
+        let daily_usage: Vec<DailyUsage> = days.iter().map(|day| day.to_usage()).collect();
+        // Summed in date order so the total doesn't depend on map order.
+        let total_cost = days.iter().map(|day| day.cost).sum();
+
+        let mut monthly_usage: Vec<MonthlyUsage> = self
+            .usage_by_month
+            .values()
+            .map(MonthData::to_usage)
+            .collect();
+        monthly_usage.sort_by(|a, b| a.month.cmp(&b.month));
+
+        let mut model_usage: Vec<ModelUsage> = self
+            .usage_by_model
+            .values()
+            .map(ModelData::to_usage)
+            .collect();
+        model_usage.sort_by_key(|b| Reverse(b.total_tokens));
+
+        let mut project_data: Vec<ProjectData> = self
+            .projects
+            .values()
+            .map(ProjectInternal::to_data)
+            .collect();
+        project_data.sort_by(|a, b| {
+            b.last_activity
+                .as_ref()
+                .unwrap_or(&String::new())
+                .cmp(a.last_activity.as_ref().unwrap_or(&String::new()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut project_model_usage: Vec<ProjectModelUsage> = self
+            .usage_by_project_model
+            .iter()
+            .map(|((project, _), data)| ProjectModelUsage {
+                project: project.to_string(),
+                usage: data.to_usage(),
+                cost: data.cost,
+            })
+            .collect();
+        project_model_usage.sort_by(|a, b| {
+            a.project
+                .cmp(&b.project)
+                .then_with(|| a.usage.model.cmp(&b.usage.model))
+        });
+
+        AllProjectData {
+            daily_usage,
+            monthly_usage,
+            model_usage,
+            projects: project_data,
+            project_model_usage,
+            total_sessions: self.sessions.len(),
+            total_cost,
+            duplicates_dropped: self.duplicates_dropped,
+        }
+    }
+}
+
+/// One project's totals with its daily series, per-model totals and
+/// sessions.
+#[derive(Debug, Serialize)]
+pub struct ProjectDetail {
+    #[serde(flatten)]
+    pub project: ProjectData,
+    #[serde(rename = "firstActivity")]
+    pub first_activity: Option<String>,
+    pub daily: Vec<DailyUsage>,
+    pub models: Vec<ModelUsage>,
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// Aggregates the records of project `name` in `window`, with days in `tz`.
+pub fn project_detail(
+    records: &[&MessageRecord],
+    name: &str,
+    tz: Tz,
+    window: &TimeWindow,
+) -> ProjectDetail {
+    let records: Vec<&MessageRecord> = records
+        .iter()
+        .copied()
+        .filter(|record| *record.project == *name && window.contains(record))
         .collect();
 
-    project_data.sort_by(|a, b| {
-        b.last_activity
-            .as_ref()
-            .unwrap_or(&String::new())
-            .cmp(a.last_activity.as_ref().unwrap_or(&String::new()))
-    });
+    let mut aggregates = UsageAggregates::new(&[name.to_string()], tz);
+    for record in &records {
+        aggregates.add(record);
+    }
+    let data = aggregates.to_data();
+
+    ProjectDetail {
+        project: data.projects.into_iter().next().expect("project is listed"),
+        first_activity: records.iter().map(|r| &r.timestamp).min().cloned(),
+        daily: data.daily_usage,
+        models: data.model_usage,
+        sessions: summarize_sessions(records),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{record, utc};
+
+    #[test]
+    fn parses_dates_and_datetimes_in_the_timezone() {
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+
+        let window = TimeWindow::parse(Some("2026-03-01"), Some("2026-03-31"), tokyo).unwrap();
+        assert_eq!(window.start, Some(utc("2026-02-28T15:00:00Z")));
+        // A date `to` takes in the whole day.
+        assert_eq!(window.end, Some(utc("2026-03-31T15:00:00Z")));
+
+        let window = TimeWindow::parse(
+            Some("2026-03-01T09:30"),
+            Some("2026-03-01T10:00:00.5"),
+            tokyo,
+        )
+        .unwrap();
+        assert_eq!(window.start, Some(utc("2026-03-01T00:30:00Z")));
+        assert_eq!(window.end, Some(utc("2026-03-01T01:00:00.5Z")));
+
+        // An explicit offset wins over the timezone.
+        let window = TimeWindow::parse(Some("2026-03-01T00:00:00+02:00"), None, tokyo).unwrap();
+        assert_eq!(window.start, Some(utc("2026-02-28T22:00:00Z")));
+        assert_eq!(window.end, None);
+    }
+
+    #[test]
+    fn rejects_malformed_and_reversed_bounds() {
+        let err = TimeWindow::parse(Some("March 1st"), None, Tz::UTC).unwrap_err();
+        assert!(err.to_string().contains("expected YYYY-MM-DD"));
+
+        let err = TimeWindow::parse(Some("2026-03-02"), Some("2026-03-01"), Tz::UTC).unwrap_err();
+        assert!(err.to_string().contains("`from` must be earlier than `to`"));
+
+        // `to` on the same day as `from` still covers that day.
+        assert!(TimeWindow::parse(Some("2026-03-01"), Some("2026-03-01"), Tz::UTC).is_ok());
+    }
+
+    #[test]
+    fn dst_gaps_and_overlaps_resolve_to_a_real_instant() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        // 02:30 does not exist on 2026-03-08; it is read an hour later.
+        let window = TimeWindow::parse(Some("2026-03-08T02:30"), None, new_york).unwrap();
+        assert_eq!(window.start, Some(utc("2026-03-08T07:30:00Z")));
+
+        // 01:30 happens twice on 2026-11-01; the earlier one is taken.
+        let window = TimeWindow::parse(Some("2026-11-01T01:30"), None, new_york).unwrap();
+        assert_eq!(window.start, Some(utc("2026-11-01T05:30:00Z")));
+
+        // That day is 25 hours long.
+        let day = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        let window = TimeWindow::dates(day, day, new_york);
+        assert_eq!(
+            window.end.unwrap() - window.start.unwrap(),
+            TimeDelta::hours(25)
+        );
+    }
+
+    #[test]
+    fn contains_is_start_inclusive_and_end_exclusive() {
+        let window = TimeWindow::parse(
+            Some("2026-01-01T00:00:00Z"),
+            Some("2026-01-02T00:00:00Z"),
+            Tz::UTC,
+        )
+        .unwrap();
+        assert!(window.contains(&record(
+            "s1",
+            "2026-01-01T00:00:00Z",
+            "claude-sonnet-4-5",
+            1,
+            1
+        )));
+        assert!(window.contains(&record(
+            "s1",
+            "2026-01-01T23:59:59.999Z",
+            "claude-sonnet-4-5",
+            1,
+            1
+        )));
+        assert!(!window.contains(&record(
+            "s1",
+            "2026-01-02T00:00:00Z",
+            "claude-sonnet-4-5",
+            1,
+            1
+        )));
+
+        // Unparseable timestamps only match an unbounded window.
+        let undated = record("s1", "yesterday", "claude-sonnet-4-5", 1, 1);
+        assert!(!window.contains(&undated));
+        assert!(TimeWindow::default().contains(&undated));
+    }
+
+    #[test]
+    fn buckets_days_and_months_in_the_timezone() {
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        let records = [
+            record("s1", "2026-01-31T14:59:59Z", "claude-sonnet-4-5", 1, 1),
+            // 00:00 on 1 February in Tokyo.
+            record("s1", "2026-01-31T15:00:00Z", "claude-sonnet-4-5", 1, 10),
+            record("s1", "2026-02-01T03:00:00Z", "claude-sonnet-4-5", 1, 100),
+        ];
+
+        let mut utc_aggregates = UsageAggregates::new(&[], Tz::UTC);
+        let mut tokyo_aggregates = UsageAggregates::new(&[], tokyo);
+        for record in &records {
+            utc_aggregates.add(record);
+            tokyo_aggregates.add(record);
+        }
 
-    Ok(AllProjectData {
-        daily_usage,
-        monthly_usage,
-        model_usage,
-        projects: project_data,
-    })
+        let days = |data: &AllProjectData| -> Vec<(String, u64)> {
+            data.daily_usage
+                .iter()
+                .map(|day| (day.date.clone(), day.output_tokens))
+                .collect()
+        };
+        let months = |data: &AllProjectData| -> Vec<(String, u64)> {
+            data.monthly_usage
+                .iter()
+                .map(|month| (month.month.clone(), month.output_tokens))
+                .collect()
+        };
+
+        let utc_data = utc_aggregates.to_data();
+        assert_eq!(
+            days(&utc_data),
+            [
+                ("2026-01-31".to_string(), 11),
+                ("2026-02-01".to_string(), 100)
+            ]
+        );
+        assert_eq!(
+            months(&utc_data),
+            [("2026-01".to_string(), 11), ("2026-02".to_string(), 100)]
+        );
+
+        let tokyo_data = tokyo_aggregates.to_data();
+        assert_eq!(
+            days(&tokyo_data),
+            [
+                ("2026-01-31".to_string(), 1),
+                ("2026-02-01".to_string(), 110)
+            ]
+        );
+        assert_eq!(
+            months(&tokyo_data),
+            [("2026-01".to_string(), 1), ("2026-02".to_string(), 110)]
+        );
+    }
+
+    fn parse(
+        line: &str,
+        session: &mut Option<Arc<str>>,
+    ) -> serde_json::Result<Option<MessageRecord>> {
+        parse_message_line(line, &"project".into(), session, &PricingTable::builtin())
+    }
+
+    #[test]
+    fn parses_usage_ids_and_tool_blocks() {
+        let line = concat!(
+            r#"{"type":"assistant","sessionId":"s1","requestId":"req_1","#,
+            r#""timestamp":"2026-03-01T09:30:00.000Z","message":{"id":"msg_1","#,
+            r#""model":"claude-sonnet-4-5","content":[{"type":"text","text":"hi"},"#,
+            r#"{"type":"tool_use","id":"tu_1","name":"Bash","input":{"command":"ls"}}],"#,
+            r#""usage":{"input_tokens":100,"output_tokens":20,"#,
+            r#""cache_creation_input_tokens":30,"cache_read_input_tokens":400}}}"#
+        );
+        let record = parse(line, &mut None).unwrap().unwrap();
+
+        assert_eq!(&*record.project, "project");
+        assert_eq!(record.session_id.as_deref(), Some("s1"));
+        assert_eq!(record.sent_at, Some(utc("2026-03-01T09:30:00Z")));
+        assert_eq!(&*record.model, "claude-sonnet-4-5");
+        assert_eq!(record.dedup_key.as_deref(), Some("msg_1:req_1"));
+        assert!(!record.duplicate);
+
+        let metrics = record.metrics.unwrap();
+        assert_eq!(metrics.input_tokens, 100);
+        assert_eq!(metrics.output_tokens, 20);
+        assert_eq!(metrics.cached_tokens, 430);
+        assert_eq!(metrics.total_tokens, 550);
+        assert_eq!(metrics.new_input_tokens, 70);
+        assert!(metrics.cost > 0.0);
+
+        assert_eq!(record.tool_uses.len(), 1);
+        assert_eq!(&*record.tool_uses[0].name, "Bash");
+        assert!(record.tool_results.is_empty());
+    }
+
+    #[test]
+    fn parses_messages_without_usage_or_ids() {
+        let line = concat!(
+            r#"{"type":"user","sessionId":"s1","timestamp":"not a time","#,
+            r#""message":{"role":"user","content":[{"type":"tool_result","#,
+            r#""tool_use_id":"tu_1","is_error":true}]}}"#
+        );
+        let record = parse(line, &mut None).unwrap().unwrap();
+
+        assert_eq!(&*record.model, "unknown");
+        assert!(record.metrics.is_none());
+        assert!(record.dedup_key.is_none());
+        // Kept, but only an unbounded window counts it.
+        assert_eq!(record.timestamp, "not a time");
+        assert!(record.sent_at.is_none());
+        assert_eq!(&*record.tool_results[0].tool_use_id, "tu_1");
+        assert!(record.tool_results[0].is_error);
+
+        // Plain-text content has no blocks.
+        let line =
+            r#"{"timestamp":"2026-03-01T09:30:00Z","message":{"role":"user","content":"hi"}}"#;
+        let record = parse(line, &mut None).unwrap().unwrap();
+        assert!(record.session_id.is_none());
+        assert!(record.tool_uses.is_empty() && record.tool_results.is_empty());
+    }
+
+    #[test]
+    fn tells_other_entries_from_malformed_lines() {
+        let mut session = None;
+        for line in [
+            "",
+            "   ",
+            r#"{"type":"summary","summary":"Fixing the build","leafUuid":"u1"}"#,
+            r#"{"type":"file-history-snapshot","snapshot":{}}"#,
+            r#"{"message":{"role":"user","content":"no timestamp"}}"#,
+            r#"{"timestamp":"2026-03-01T09:30:00Z","message":"not an object"}"#,
+            "[1, 2, 3]",
+        ] {
+            assert!(parse(line, &mut session).unwrap().is_none(), "{:?}", line);
+        }
+
+        for line in [
+            "not json",
+            r#"{"timestamp":"2026-03-01T09:30:00Z","message":{"#,
+            r#"{"a":1} trailing"#,
+        ] {
+            assert!(parse(line, &mut session).is_err(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn consecutive_records_share_the_session_id() {
+        let line = |session: &str| {
+            format!(
+                r#"{{"sessionId":"{}","timestamp":"2026-03-01T09:30:00Z","message":{{"role":"user","content":"hi"}}}}"#,
+                session
+            )
+        };
+        let mut session = None;
+        let first = parse(&line("s1"), &mut session).unwrap().unwrap();
+        let second = parse(&line("s1"), &mut session).unwrap().unwrap();
+        let third = parse(&line("s2"), &mut session).unwrap().unwrap();
+
+        let id = |record: &MessageRecord| record.session_id.clone().unwrap();
+        assert!(Arc::ptr_eq(&id(&first), &id(&second)));
+        assert_eq!(&*id(&third), "s2");
+        assert_eq!(session.as_deref(), Some("s2"));
+    }
 }
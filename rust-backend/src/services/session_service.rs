@@ -0,0 +1,152 @@
+use crate::models::{MessageRecord, MessageUsage};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Totals for one conversation, which may span several session files when
+/// it was resumed.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    pub project: String,
+    #[serde(rename = "startTime")]
+    pub start_time: String,
+    #[serde(rename = "endTime")]
+    pub end_time: String,
+    #[serde(rename = "durationSeconds")]
+    pub duration_seconds: i64,
+    #[serde(rename = "messageCount")]
+    pub message_count: usize,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: u64,
+    #[serde(rename = "cachedTokens")]
+    pub cached_tokens: u64,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+    /// Models used, in order of first use.
+    pub models: Vec<String>,
+}
+
+struct SessionData<'a> {
+    first: &'a MessageRecord,
+    last: &'a MessageRecord,
+    message_count: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+    cached_tokens: u64,
+    total_tokens: u64,
+    cost: f64,
+    models: Vec<&'a str>,
+}
+
+impl<'a> SessionData<'a> {
+    fn new(record: &'a MessageRecord) -> Self {
+        SessionData {
+            first: record,
+            last: record,
+            message_count: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            cached_tokens: 0,
+            total_tokens: 0,
+            cost: 0.0,
+            models: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, record: &'a MessageRecord) {
+        self.message_count += 1;
+        if (record.sent_at, &record.timestamp) < (self.first.sent_at, &self.first.timestamp) {
+            self.first = record;
+        }
+        if (record.sent_at, &record.timestamp) > (self.last.sent_at, &self.last.timestamp) {
+            self.last = record;
+        }
+        if let Some(metrics) = &record.metrics {
+            self.input_tokens += metrics.input_tokens;
+            self.output_tokens += metrics.output_tokens;
+            self.cached_tokens += metrics.cached_tokens;
+            self.total_tokens += metrics.total_tokens;
+            self.cost += metrics.cost;
+            if !self.models.contains(&&*record.model) {
+                self.models.push(&record.model);
+            }
+        }
+    }
+
+    fn to_summary(&self, session_id: &str) -> SessionSummary {
+        let duration_seconds = match (self.first.sent_at, self.last.sent_at) {
+            (Some(start), Some(end)) => (end - start).num_seconds(),
+            _ => 0,
+        };
+        SessionSummary {
+            session_id: session_id.to_string(),
+            project: self.first.project.to_string(),
+            start_time: self.first.timestamp.clone(),
+            end_time: self.last.timestamp.clone(),
+            duration_seconds,
+            message_count: self.message_count,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cached_tokens: self.cached_tokens,
+            total_tokens: self.total_tokens,
+            cost: format!("{:.4}", self.cost),
+            models: self.models.iter().map(|model| model.to_string()).collect(),
+        }
+    }
+}
+
+/// Groups records by session id, most recently active session first.
+/// Records without a session id are skipped.
+pub fn summarize_sessions<'a>(
+    records: impl IntoIterator<Item = &'a MessageRecord>,
+) -> Vec<SessionSummary> {
+    let mut sessions: HashMap<&str, SessionData> = HashMap::new();
+    for record in records {
+        let Some(session_id) = record.session_id.as_deref() else {
+            continue;
+        };
+        sessions
+            .entry(session_id)
+            .or_insert_with(|| SessionData::new(record))
+            .add(record);
+    }
+
+    let mut summaries: Vec<SessionSummary> = sessions
+        .iter()
+        .map(|(session_id, data)| data.to_summary(session_id))
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.end_time
+            .cmp(&a.end_time)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    summaries
+}
+
+/// A session's totals and every message in it that reported usage, oldest
+/// first.
+#[derive(Debug, Serialize)]
+pub struct SessionDetail {
+    #[serde(flatten)]
+    pub session: SessionSummary,
+    pub messages: Vec<MessageUsage>,
+}
+
+pub fn session_detail(records: &[&MessageRecord], session_id: &str) -> Option<SessionDetail> {
+    let mut records: Vec<&MessageRecord> = records
+        .iter()
+        .copied()
+        .filter(|record| record.session_id.as_deref() == Some(session_id))
+        .collect();
+    records.sort_by(|a, b| (a.sent_at, &a.timestamp).cmp(&(b.sent_at, &b.timestamp)));
+
+    let session = summarize_sessions(records.iter().copied()).pop()?;
+    Some(SessionDetail {
+        session,
+        messages: records.iter().filter_map(|r| r.to_usage()).collect(),
+    })
+}
@@ -0,0 +1,536 @@
+use crate::models::{MessageRecord, Usage, UsageMetrics};
+use crate::services::{FileCursor, PricingTable, TimeWindow, UsageAggregates};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bump when the tables change; older indexes are dropped and rebuilt from
+/// the session logs.
+const SCHEMA_VERSION: i32 = 5;
+
+const SCHEMA: &str = "
+    CREATE TABLE files (
+        path TEXT PRIMARY KEY,
+        project TEXT NOT NULL,
+        offset INTEGER NOT NULL,
+        len INTEGER NOT NULL,
+        modified_ns INTEGER
+    );
+    CREATE TABLE messages (
+        file TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        session_id TEXT,
+        timestamp TEXT NOT NULL,
+        -- `timestamp` in nanoseconds since the epoch, NULL if it does not parse
+        sent_at INTEGER,
+        model TEXT NOT NULL,
+        has_usage INTEGER NOT NULL,
+        input_tokens INTEGER NOT NULL,
+        output_tokens INTEGER NOT NULL,
+        cache_creation_tokens INTEGER NOT NULL,
+        cache_read_tokens INTEGER NOT NULL,
+        dedup_key TEXT,
+        duplicate INTEGER NOT NULL,
+        tool_uses TEXT,
+        tool_results TEXT,
+        PRIMARY KEY (file, seq)
+    );
+    CREATE INDEX messages_sent_at ON messages (sent_at);
+";
+
+/// Length of the buckets usage is summed in by [`UsageStore::aggregate`].
+/// Every timezone offset in use is a multiple of it, so each bucket falls
+/// within one local day.
+const BUCKET_NANOS: i64 = 15 * 60 * 1_000_000_000;
+
+/// A session file restored from the index: where to resume reading it and
+/// the records already taken from it.
+pub struct StoredFile {
+    pub path: PathBuf,
+    pub project: Arc<str>,
+    pub cursor: FileCursor,
+    pub records: Vec<MessageRecord>,
+}
+
+/// A stored record whose duplicate flag changed when duplicates were flagged
+/// again from scratch.
+pub struct FlagUpdate<'a> {
+    pub path: &'a PathBuf,
+    pub seq: usize,
+    pub duplicate: bool,
+}
+
+/// Records to write for one file after a rescan.
+pub struct FileUpdate<'a> {
+    pub path: &'a PathBuf,
+    pub project: &'a str,
+    pub cursor: &'a FileCursor,
+    /// Position of `records[0]` within the file's record list.
+    pub first_seq: usize,
+    pub records: &'a [Arc<MessageRecord>],
+    /// Whether rows previously stored for the file must be dropped first.
+    pub replace: bool,
+}
+
+/// SQLite store of parsed messages and per-file read checkpoints, so a
+/// restart resumes where the previous run stopped instead of re-parsing all
+/// history, and totals over a time range are summed by indexed queries
+/// rather than a scan of every record. Token counts are stored raw and
+/// costed when read, so pricing changes apply without rebuilding the store.
+pub struct UsageStore {
+    conn: Mutex<Connection>,
+}
+
+impl UsageStore {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn =
+            Connection::open(path).context(format!("Failed to open usage index {}", path))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            if version != 0 {
+                tracing::info!("Usage index schema changed; rebuilding {}", path);
+            }
+            conn.execute_batch("DROP TABLE IF EXISTS messages; DROP TABLE IF EXISTS files;")?;
+            conn.execute_batch(SCHEMA)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+
+        Ok(UsageStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Loads every indexed file with its records, costed with `pricing`.
+    pub fn load(&self, pricing: &PricingTable) -> Result<Vec<StoredFile>> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut files: HashMap<String, StoredFile> = HashMap::new();
+        let mut stmt = conn.prepare("SELECT path, project, offset, len, modified_ns FROM files")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (path, project, offset, len, modified_ns) = row?;
+            let modified = modified_ns.map(|ns| UNIX_EPOCH + Duration::from_nanos(ns as u64));
+            let stored = StoredFile {
+                path: PathBuf::from(&path),
+                project: project.into(),
+                cursor: FileCursor::resume(offset as u64, modified, len as u64),
+                records: Vec::new(),
+            };
+            files.insert(path, stored);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT file, session_id, timestamp, model, has_usage, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_read_tokens, dedup_key, duplicate, tool_uses,
+                    tool_results
+             FROM messages ORDER BY file, seq",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut models: HashMap<String, Arc<str>> = HashMap::new();
+        let mut session: Option<Arc<str>> = None;
+        while let Some(row) = rows.next()? {
+            let file: String = row.get(0)?;
+            let Some(stored) = files.get_mut(&file) else {
+                continue;
+            };
+
+            let session_id = row.get::<_, Option<String>>(1)?.map(|id| match &session {
+                Some(current) if **current == *id => current.clone(),
+                _ => {
+                    let id: Arc<str> = id.into();
+                    session = Some(id.clone());
+                    id
+                }
+            });
+            let timestamp: String = row.get(2)?;
+            let model: String = row.get(3)?;
+            let model = models
+                .entry(model)
+                .or_insert_with_key(|model| model.as_str().into())
+                .clone();
+
+            let sent_at = DateTime::parse_from_rfc3339(&timestamp)
+                .ok()
+                .map(|ts| ts.with_timezone(&Utc));
+            let metrics = if row.get::<_, bool>(4)? {
+                let usage = Usage {
+                    input_tokens: Some(row.get::<_, i64>(5)? as u64),
+                    output_tokens: Some(row.get::<_, i64>(6)? as u64),
+                    cache_creation_tokens: Some(row.get::<_, i64>(7)? as u64),
+                    cache_read_tokens: Some(row.get::<_, i64>(8)? as u64),
+                };
+                Some(UsageMetrics::from_usage(
+                    &usage,
+                    &pricing.rates_for(&model, sent_at),
+                ))
+            } else {
+                None
+            };
+
+            stored.records.push(MessageRecord {
+                project: stored.project.clone(),
+                session_id,
+                timestamp,
+                sent_at,
+                model,
+                metrics,
+                dedup_key: row.get::<_, Option<String>>(9)?.map(String::into_boxed_str),
+                duplicate: row.get(10)?,
+                tool_uses: from_json_column(row.get(11)?)?,
+                tool_results: from_json_column(row.get(12)?)?,
+            });
+        }
+
+        Ok(files.into_values().collect())
+    }
+
+    /// Writes new records and checkpoints, updates the duplicate flag of
+    /// records already stored, and forgets removed files, in one transaction.
+    pub fn save(
+        &self,
+        updates: &[FileUpdate],
+        flags: &[FlagUpdate],
+        removed: &[PathBuf],
+    ) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction()?;
+        {
+            let mut delete_messages = tx.prepare("DELETE FROM messages WHERE file = ?1")?;
+            let mut delete_file = tx.prepare("DELETE FROM files WHERE path = ?1")?;
+            let mut upsert_file = tx.prepare(
+                "INSERT OR REPLACE INTO files (path, project, offset, len, modified_ns)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut insert_message = tx.prepare(
+                "INSERT OR REPLACE INTO messages (file, seq, session_id, timestamp, sent_at, model,
+                    has_usage, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens,
+                    dedup_key, duplicate, tool_uses, tool_results)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?;
+            let mut update_flag =
+                tx.prepare("UPDATE messages SET duplicate = ?3 WHERE file = ?1 AND seq = ?2")?;
+
+            for path in removed {
+                let path = path.to_string_lossy();
+                delete_messages.execute([&path])?;
+                delete_file.execute([&path])?;
+            }
+
+            for update in updates {
+                let path = update.path.to_string_lossy();
+                if update.replace {
+                    delete_messages.execute([&path])?;
+                }
+
+                let modified_ns = update
+                    .cursor
+                    .modified
+                    .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos() as i64);
+                upsert_file.execute(params![
+                    path,
+                    update.project,
+                    update.cursor.offset as i64,
+                    update.cursor.len as i64,
+                    modified_ns,
+                ])?;
+
+                for (i, record) in update.records.iter().enumerate() {
+                    let metrics = record.metrics.clone().unwrap_or_default();
+                    insert_message.execute(params![
+                        path,
+                        (update.first_seq + i) as i64,
+                        record.session_id.as_deref(),
+                        record.timestamp,
+                        record.sent_at.and_then(|ts| ts.timestamp_nanos_opt()),
+                        &*record.model,
+                        record.metrics.is_some(),
+                        metrics.input_tokens as i64,
+                        metrics.output_tokens as i64,
+                        metrics.cache_creation_tokens as i64,
+                        metrics.cache_read_tokens as i64,
+                        record.dedup_key.as_deref(),
+                        record.duplicate,
+                        to_json_column(&record.tool_uses)?,
+                        to_json_column(&record.tool_results)?,
+                    ])?;
+                }
+            }
+
+            for flag in flags {
+                let path = flag.path.to_string_lossy();
+                update_flag.execute(params![path, flag.seq as i64, flag.duplicate])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Totals of the messages in `window`, which must be bounded, with days
+    /// and months in `tz`. Usage is summed per project, session, model and
+    /// quarter hour, and each sum costed at the rates in effect at the start
+    /// of its quarter hour.
+    pub fn aggregate(
+        &self,
+        window: &TimeWindow,
+        project_names: &[String],
+        tz: Tz,
+        pricing: &PricingTable,
+    ) -> Result<UsageAggregates> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let start = window.start.map_or(i64::MIN, nanos);
+        let end = window.end.map_or(i64::MAX, nanos);
+        let mut aggregates = UsageAggregates::new(project_names, tz);
+
+        let mut stmt = conn.prepare(
+            "SELECT f.project, m.session_id, m.model, m.sent_at / ?3, COUNT(*),
+                    SUM(m.input_tokens), SUM(m.output_tokens), SUM(m.cache_creation_tokens),
+                    SUM(m.cache_read_tokens), SUM(MAX(m.input_tokens - m.cache_creation_tokens, 0))
+             FROM messages m JOIN files f ON f.path = m.file
+             WHERE m.sent_at >= ?1 AND m.sent_at < ?2 AND m.has_usage AND NOT m.duplicate
+             GROUP BY f.project, m.session_id, m.model, m.sent_at / ?3",
+        )?;
+        let mut rows = stmt.query(params![start, end, BUCKET_NANOS])?;
+        let mut names: HashMap<String, Arc<str>> = HashMap::new();
+        let mut intern = |name: String| {
+            names
+                .entry(name)
+                .or_insert_with_key(|name| name.as_str().into())
+                .clone()
+        };
+        while let Some(row) = rows.next()? {
+            let project = intern(row.get(0)?);
+            let session_id = row.get::<_, Option<String>>(1)?.map(&mut intern);
+            let model = intern(row.get(2)?);
+            let bucket_start = DateTime::from_timestamp_nanos(row.get::<_, i64>(3)? * BUCKET_NANOS);
+            let usage = Usage {
+                input_tokens: Some(row.get::<_, i64>(5)? as u64),
+                output_tokens: Some(row.get::<_, i64>(6)? as u64),
+                cache_creation_tokens: Some(row.get::<_, i64>(7)? as u64),
+                cache_read_tokens: Some(row.get::<_, i64>(8)? as u64),
+            };
+            let mut metrics =
+                UsageMetrics::from_usage(&usage, &pricing.rates_for(&model, Some(bucket_start)));
+            // Clamped per message, so not derivable from the sums.
+            metrics.new_input_tokens = row.get::<_, i64>(9)? as u64;
+            aggregates.add_summed(
+                &project,
+                session_id.as_ref(),
+                &model,
+                bucket_start,
+                &metrics,
+                row.get::<_, i64>(4)? as usize,
+            );
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT f.project, COUNT(*), MAX(m.timestamp)
+             FROM messages m JOIN files f ON f.path = m.file
+             WHERE m.sent_at >= ?1 AND m.sent_at < ?2 AND NOT m.duplicate
+             GROUP BY f.project",
+        )?;
+        let rows = stmt.query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (project, messages, last_activity) = row?;
+            aggregates.add_activity(&project, messages as usize, last_activity);
+        }
+
+        let duplicates: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM messages
+             WHERE sent_at >= ?1 AND sent_at < ?2 AND duplicate",
+            params![start, end],
+            |row| row.get(0),
+        )?;
+        aggregates.add_duplicates(duplicates as usize);
+
+        Ok(aggregates)
+    }
+}
+
+/// Nanoseconds since the epoch, saturating outside the years 1677-2262.
+fn nanos(ts: DateTime<Utc>) -> i64 {
+    ts.timestamp_nanos_opt().unwrap_or(if ts.timestamp() < 0 {
+        i64::MIN
+    } else {
+        i64::MAX
+    })
+}
+
+/// Tool blocks are stored as a JSON array, or NULL when there are none.
+fn to_json_column<T: Serialize>(items: &[T]) -> Result<Option<String>> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(items)?))
+}
+
+fn from_json_column<T: DeserializeOwned>(column: Option<String>) -> Result<Vec<T>> {
+    match column {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::parse_message_line;
+    use std::fs;
+
+    #[test]
+    fn records_and_checkpoints_survive_a_reopen() {
+        let path =
+            std::env::temp_dir().join(format!("rust-backend-{}-store.sqlite", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let pricing = PricingTable::builtin();
+        let project: Arc<str> = "project".into();
+        let mut session = None;
+        let records: Vec<Arc<MessageRecord>> = [
+            r#"{"sessionId":"s1","requestId":"r1","timestamp":"2026-01-01T10:00:00Z","message":{"id":"m1","model":"claude-opus-4-5","usage":{"input_tokens":100,"output_tokens":20,"cache_read_input_tokens":5}}}"#,
+            r#"{"sessionId":"s1","timestamp":"2026-01-01T10:01:00Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","is_error":true}]}}"#,
+        ]
+        .iter()
+        .map(|line| {
+            let record = parse_message_line(line, &project, &mut session, &pricing).unwrap();
+            Arc::new(record.unwrap())
+        })
+        .collect();
+
+        let file = PathBuf::from("/logs/project/session.jsonl");
+        let cursor = FileCursor::resume(
+            512,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            512,
+        );
+        {
+            let store = UsageStore::open(path.to_str().unwrap()).unwrap();
+            let update = FileUpdate {
+                path: &file,
+                project: &project,
+                cursor: &cursor,
+                first_seq: 0,
+                records: &records,
+                replace: false,
+            };
+            store.save(&[update], &[], &[]).unwrap();
+        }
+
+        let store = UsageStore::open(path.to_str().unwrap()).unwrap();
+        let stored = store.load(&pricing).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(stored.len(), 1);
+        let stored = &stored[0];
+        assert_eq!(stored.path, file);
+        assert_eq!(&*stored.project, "project");
+        assert!(stored.cursor.is_current(cursor.modified, 512));
+        assert_eq!(stored.cursor.offset, 512);
+
+        assert_eq!(stored.records.len(), 2);
+        let (first, second) = (&stored.records[0], &stored.records[1]);
+        assert_eq!(first.dedup_key.as_deref(), Some("m1:r1"));
+        assert_eq!(first.session_id.as_deref(), Some("s1"));
+        let metrics = first.metrics.as_ref().unwrap();
+        let original = records[0].metrics.as_ref().unwrap();
+        assert_eq!(metrics.input_tokens, 100);
+        assert_eq!(metrics.cache_read_tokens, 5);
+        assert_eq!(metrics.cost, original.cost);
+        assert!(second.metrics.is_none());
+        assert_eq!(&*second.tool_results[0].tool_use_id, "t1");
+        assert!(second.tool_results[0].is_error);
+    }
+
+    #[test]
+    fn windowed_aggregates_match_the_records() {
+        let path = std::env::temp_dir().join(format!(
+            "rust-backend-{}-store-aggregate.sqlite",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let pricing = PricingTable::builtin();
+        let project: Arc<str> = "project".into();
+        let mut session = None;
+        let mut records: Vec<Arc<MessageRecord>> = [
+            // Before the window.
+            r#"{"sessionId":"s1","timestamp":"2026-01-01T14:59:00Z","message":{"model":"claude-opus-4-5","usage":{"input_tokens":7,"output_tokens":1}}}"#,
+            // 2026-01-02 in Tokyo.
+            r#"{"sessionId":"s1","requestId":"r1","timestamp":"2026-01-01T15:00:00Z","message":{"id":"m1","model":"claude-opus-4-5","usage":{"input_tokens":100,"output_tokens":20,"cache_creation_input_tokens":150,"cache_read_input_tokens":5}}}"#,
+            r#"{"sessionId":"s1","requestId":"r1","timestamp":"2026-01-01T15:00:00Z","message":{"id":"m1","model":"claude-opus-4-5","usage":{"input_tokens":100,"output_tokens":20}}}"#,
+            r#"{"sessionId":"s1","timestamp":"2026-01-01T15:05:00Z","message":{"model":"claude-opus-4-5","usage":{"input_tokens":300,"output_tokens":40}}}"#,
+            r#"{"sessionId":"s2","timestamp":"2026-01-01T15:20:00Z","message":{"model":"claude-sonnet-4-5","usage":{"input_tokens":50,"output_tokens":10}}}"#,
+            r#"{"sessionId":"s2","timestamp":"2026-01-01T15:21:00Z","message":{"role":"user","content":"hi"}}"#,
+            // 2026-02-01 in Tokyo, and after the window.
+            r#"{"sessionId":"s3","timestamp":"2026-01-31T16:00:00Z","message":{"model":"claude-opus-4-5","usage":{"input_tokens":9,"output_tokens":3}}}"#,
+            r#"{"sessionId":"s3","timestamp":"2026-02-01T15:00:00Z","message":{"model":"claude-opus-4-5","usage":{"input_tokens":9,"output_tokens":3}}}"#,
+        ]
+        .iter()
+        .map(|line| {
+            let record = parse_message_line(line, &project, &mut session, &pricing).unwrap();
+            Arc::new(record.unwrap())
+        })
+        .collect();
+        Arc::make_mut(&mut records[2]).duplicate = true;
+
+        let file = PathBuf::from("/logs/project/session.jsonl");
+        let store = UsageStore::open(path.to_str().unwrap()).unwrap();
+        let update = FileUpdate {
+            path: &file,
+            project: &project,
+            cursor: &FileCursor::default(),
+            first_seq: 0,
+            records: &records,
+            replace: false,
+        };
+        store.save(&[update], &[], &[]).unwrap();
+
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+        let window = TimeWindow::parse(Some("2026-01-02"), Some("2026-02-01"), tz).unwrap();
+        let names = vec!["project".to_string(), "idle".to_string()];
+        let summed = store.aggregate(&window, &names, tz, &pricing).unwrap().to_data();
+        let _ = fs::remove_file(&path);
+
+        let mut expected = UsageAggregates::new(&names, tz);
+        for record in records.iter().filter(|record| window.contains(record)) {
+            expected.add(record);
+        }
+        let expected = expected.to_data();
+
+        assert_eq!(json(&summed.daily_usage), json(&expected.daily_usage));
+        assert_eq!(json(&summed.monthly_usage), json(&expected.monthly_usage));
+        assert_eq!(json(&summed.model_usage), json(&expected.model_usage));
+        assert_eq!(json(&summed.projects), json(&expected.projects));
+        assert_eq!(summed.daily_usage.len(), 2);
+        assert_eq!(summed.daily_usage[0].new_input_tokens, 300 + 50);
+        assert_eq!(summed.total_sessions, expected.total_sessions);
+        assert_eq!(summed.duplicates_dropped, 1);
+        assert!((summed.total_cost - expected.total_cost).abs() < 1e-12);
+    }
+
+    fn json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+}
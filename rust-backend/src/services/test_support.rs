@@ -0,0 +1,43 @@
+//! Fixtures shared by the service tests.
+
+use crate::models::MessageRecord;
+use crate::services::{parse_message_line, PricingTable};
+use chrono::{DateTime, Utc};
+
+pub(crate) fn utc(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// A record with usage in project `project`, parsed from a log line and
+/// costed at the built-in rates.
+pub(crate) fn record(
+    session: &str,
+    timestamp: &str,
+    model: &str,
+    input_tokens: u64,
+    output_tokens: u64,
+) -> MessageRecord {
+    let line = format!(
+        r#"{{"sessionId":"{}","timestamp":"{}","message":{{"model":"{}","usage":{{"input_tokens":{},"output_tokens":{}}}}}}}"#,
+        session, timestamp, model, input_tokens, output_tokens
+    );
+    parse_message_line(
+        &line,
+        &"project".into(),
+        &mut None,
+        &PricingTable::builtin(),
+    )
+    .unwrap()
+    .unwrap()
+}
+
+pub(crate) fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
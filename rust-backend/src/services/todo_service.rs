@@ -0,0 +1,260 @@
+use crate::models::MessageRecord;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One entry of a todo list as Claude Code writes it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TodoItem {
+    pub id: Option<String>,
+    pub content: String,
+    pub status: String,
+    pub priority: Option<String>,
+}
+
+/// A todo list file, `<sessionId>-agent-<agentId>.json`.
+#[derive(Debug)]
+pub struct TodoFile {
+    pub session_id: String,
+    /// Last modification time of the file.
+    pub updated_at: Option<DateTime<Utc>>,
+    pub items: Vec<TodoItem>,
+}
+
+/// Which todo lists a report covers.
+#[derive(Debug, Default)]
+pub struct TodoFilter {
+    pub project: Option<String>,
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TodoStatusCounts {
+    pub pending: usize,
+    #[serde(rename = "inProgress")]
+    pub in_progress: usize,
+    pub completed: usize,
+}
+
+impl TodoStatusCounts {
+    fn add(&mut self, status: &str) {
+        match status {
+            "pending" => self.pending += 1,
+            "in_progress" => self.in_progress += 1,
+            "completed" => self.completed += 1,
+            _ => {}
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.pending + self.in_progress + self.completed
+    }
+
+    fn completion_rate(&self) -> f64 {
+        self.completed as f64 / self.total().max(1) as f64
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TodoSession {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    /// `None` when no usage log mentions the session.
+    pub project: Option<String>,
+    pub files: usize,
+    pub total: usize,
+    #[serde(flatten)]
+    pub counts: TodoStatusCounts,
+    #[serde(rename = "completionRate")]
+    pub completion_rate: f64,
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// A todo that is pending or in progress.
+#[derive(Debug, Serialize)]
+pub struct OpenTodo {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    pub project: Option<String>,
+    pub id: Option<String>,
+    pub content: String,
+    pub status: String,
+    pub priority: Option<String>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TodoReport {
+    #[serde(rename = "totalFiles")]
+    pub total_files: usize,
+    #[serde(rename = "totalTodos")]
+    pub total_todos: usize,
+    #[serde(rename = "statusCounts")]
+    pub status_counts: TodoStatusCounts,
+    #[serde(rename = "completionRate")]
+    pub completion_rate: f64,
+    /// Sessions with at least one todo, most recently updated first.
+    pub sessions: Vec<TodoSession>,
+    /// In-progress items first, then pending; most recently updated first
+    /// within each.
+    #[serde(rename = "openItems")]
+    pub open_items: Vec<OpenTodo>,
+}
+
+/// The session id part of `<sessionId>-agent-<agentId>`.
+fn session_id_of(stem: &str) -> &str {
+    stem.split_once("-agent-")
+        .map_or(stem, |(session_id, _)| session_id)
+}
+
+fn todo_files(todos_path: &Path) -> Result<Vec<PathBuf>> {
+    if !todos_path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<_> = fs::read_dir(todos_path)
+        .context(format!("Failed to read directory: {:?}", todos_path))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Number of todo list files, without reading them.
+pub fn count_todo_files(todos_path: &str) -> Result<usize> {
+    Ok(todo_files(Path::new(todos_path))?.len())
+}
+
+/// Reads every todo list in `todos_path`. A missing directory yields none;
+/// files that are not a JSON array of todos are skipped.
+pub fn read_todo_files(todos_path: &str) -> Result<Vec<TodoFile>> {
+    let mut files = Vec::new();
+    for path in todo_files(Path::new(todos_path))? {
+        let items = match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str::<Vec<TodoItem>>(&content)?))
+        {
+            Ok(items) => items,
+            Err(e) => {
+                tracing::warn!("Skipping todo file {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        files.push(TodoFile {
+            session_id: session_id_of(&stem).to_string(),
+            updated_at: fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(DateTime::<Utc>::from),
+            items,
+        });
+    }
+    Ok(files)
+}
+
+/// Summarizes `files`, taking each session's project from the usage
+/// `records`.
+pub fn todo_report(
+    files: &[TodoFile],
+    records: &[&MessageRecord],
+    filter: &TodoFilter,
+) -> TodoReport {
+    let mut projects: HashMap<&str, &str> = HashMap::new();
+    for record in records {
+        if let Some(session_id) = record.session_id.as_deref() {
+            projects.entry(session_id).or_insert(&record.project);
+        }
+    }
+
+    let mut status_counts = TodoStatusCounts::default();
+    let mut total_files = 0;
+    let mut total_todos = 0;
+    let mut sessions: BTreeMap<&str, TodoSession> = BTreeMap::new();
+    let mut open_items = Vec::new();
+
+    for file in files {
+        let project = projects.get(file.session_id.as_str()).copied();
+        let matches = filter
+            .project
+            .as_deref()
+            .is_none_or(|wanted| project == Some(wanted))
+            && filter
+                .session_id
+                .as_deref()
+                .is_none_or(|wanted| file.session_id == wanted);
+        if !matches {
+            continue;
+        }
+
+        total_files += 1;
+        total_todos += file.items.len();
+        if file.items.is_empty() {
+            continue;
+        }
+
+        let session = sessions
+            .entry(&file.session_id)
+            .or_insert_with(|| TodoSession {
+                session_id: file.session_id.clone(),
+                project: project.map(String::from),
+                files: 0,
+                total: 0,
+                counts: TodoStatusCounts::default(),
+                completion_rate: 0.0,
+                last_updated: None,
+            });
+        session.files += 1;
+        session.total += file.items.len();
+        session.last_updated = session.last_updated.max(file.updated_at);
+
+        for item in &file.items {
+            status_counts.add(&item.status);
+            session.counts.add(&item.status);
+            if matches!(item.status.as_str(), "pending" | "in_progress") {
+                open_items.push(OpenTodo {
+                    session_id: file.session_id.clone(),
+                    project: project.map(String::from),
+                    id: item.id.clone(),
+                    content: item.content.clone(),
+                    status: item.status.clone(),
+                    priority: item.priority.clone(),
+                    updated_at: file.updated_at,
+                });
+            }
+        }
+    }
+
+    let mut sessions: Vec<TodoSession> = sessions
+        .into_values()
+        .map(|mut session| {
+            session.completion_rate = session.counts.completion_rate();
+            session
+        })
+        .collect();
+    sessions.sort_by_key(|session| Reverse(session.last_updated));
+    open_items.sort_by(|a, b| {
+        (b.status == "in_progress")
+            .cmp(&(a.status == "in_progress"))
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
+
+    TodoReport {
+        total_files,
+        total_todos,
+        completion_rate: status_counts.completion_rate(),
+        status_counts,
+        sessions,
+        open_items,
+    }
+}
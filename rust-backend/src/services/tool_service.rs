@@ -0,0 +1,321 @@
+use crate::models::{MessageRecord, ToolUse};
+use crate::services::TimeWindow;
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Turns listed in a tool report, most expensive first.
+const TOP_TURNS: usize = 10;
+
+/// Which tool calls a report covers.
+#[derive(Debug, Default)]
+pub struct ToolFilter {
+    pub window: TimeWindow,
+    pub project: Option<String>,
+}
+
+impl ToolFilter {
+    fn matches(&self, record: &MessageRecord) -> bool {
+        self.window.contains(record)
+            && self
+                .project
+                .as_deref()
+                .is_none_or(|project| *record.project == *project)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolStats {
+    pub name: String,
+    /// Server name for MCP tools (`mcp__<server>__<tool>`).
+    #[serde(rename = "mcpServer")]
+    pub mcp_server: Option<String>,
+    pub calls: usize,
+    /// Calls whose result was flagged as an error.
+    pub errors: usize,
+    #[serde(rename = "errorRate")]
+    pub error_rate: f64,
+    pub sessions: usize,
+    pub projects: usize,
+    /// Tokens of the turns that called the tool, split evenly between the
+    /// calls in each turn.
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+    #[serde(rename = "firstUsed")]
+    pub first_used: String,
+    #[serde(rename = "lastUsed")]
+    pub last_used: String,
+}
+
+/// Tool calls within one project or on one day.
+#[derive(Debug, Serialize)]
+pub struct ToolBreakdown {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    pub calls: usize,
+    pub errors: usize,
+    /// Calls per tool name.
+    pub tools: BTreeMap<String, usize>,
+}
+
+/// One API response that called tools, with what it cost.
+#[derive(Debug, Serialize)]
+pub struct ToolTurn {
+    #[serde(rename = "sessionId")]
+    pub session_id: Option<String>,
+    pub project: String,
+    pub timestamp: String,
+    pub model: String,
+    /// Tools called, in order.
+    pub tools: Vec<String>,
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: u64,
+    pub cost: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolReport {
+    #[serde(rename = "totalCalls")]
+    pub total_calls: usize,
+    #[serde(rename = "totalErrors")]
+    pub total_errors: usize,
+    #[serde(rename = "errorRate")]
+    pub error_rate: f64,
+    #[serde(rename = "uniqueTools")]
+    pub unique_tools: usize,
+    /// API responses that called at least one tool.
+    pub turns: usize,
+    /// Most called first.
+    pub tools: Vec<ToolStats>,
+    /// Most calls first.
+    pub projects: Vec<ToolBreakdown>,
+    /// Oldest first, only days with calls.
+    pub daily: Vec<ToolBreakdown>,
+    #[serde(rename = "topTurns")]
+    pub top_turns: Vec<ToolTurn>,
+}
+
+/// An API response. Claude Code logs each content block of a response as
+/// its own line, so one turn may span several records sharing a dedup key.
+struct Turn<'a> {
+    first: &'a MessageRecord,
+    tools: Vec<&'a ToolUse>,
+    total_tokens: u64,
+    cost: f64,
+}
+
+#[derive(Default)]
+struct ToolTotals<'a> {
+    calls: usize,
+    errors: usize,
+    sessions: HashSet<&'a str>,
+    projects: HashSet<&'a str>,
+    total_tokens: f64,
+    cost: f64,
+    first_used: Option<&'a str>,
+    last_used: Option<&'a str>,
+}
+
+#[derive(Default)]
+struct BreakdownTotals<'a> {
+    calls: usize,
+    errors: usize,
+    tools: BTreeMap<&'a str, usize>,
+}
+
+impl BreakdownTotals<'_> {
+    fn to_breakdown(&self, project: Option<&str>, date: Option<&str>) -> ToolBreakdown {
+        ToolBreakdown {
+            project: project.map(String::from),
+            date: date.map(String::from),
+            calls: self.calls,
+            errors: self.errors,
+            tools: self
+                .tools
+                .iter()
+                .map(|(name, calls)| (name.to_string(), *calls))
+                .collect(),
+        }
+    }
+}
+
+fn mcp_server(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("mcp__")?;
+    let (server, _) = rest.split_once("__")?;
+    Some(server.to_string())
+}
+
+fn local_date(record: &MessageRecord, tz: Tz) -> String {
+    match record.sent_at {
+        Some(sent_at) => sent_at.with_timezone(&tz).date_naive().to_string(),
+        None => record.timestamp.split('T').next().unwrap_or("").to_string(),
+    }
+}
+
+/// Tool calls made by messages passing `filter`, with outcomes taken from
+/// the `tool_result` blocks that answered them.
+///
+/// `records` must include duplicates: a turn's tool calls are often on lines
+/// that share the dedup key of its first line. Calls are deduped by tool use
+/// id instead, and a turn's tokens are counted once.
+pub fn tool_usage(records: &[&MessageRecord], filter: &ToolFilter, tz: Tz) -> ToolReport {
+    // A result is an error if any copy of it says so.
+    let mut failed: HashMap<&str, bool> = HashMap::new();
+    for record in records {
+        for result in &record.tool_results {
+            *failed.entry(&result.tool_use_id).or_default() |= result.is_error;
+        }
+    }
+
+    // Group tool calls into turns, keyed by dedup key or, lacking one, by
+    // the record itself.
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut turn_index: HashMap<&str, usize> = HashMap::new();
+    let mut seen_calls: HashSet<&str> = HashSet::new();
+    for record in records {
+        if record.tool_uses.is_empty() || !filter.matches(record) {
+            continue;
+        }
+        let index = match record.dedup_key.as_deref() {
+            Some(key) => *turn_index.entry(key).or_insert(turns.len()),
+            None => turns.len(),
+        };
+        if index == turns.len() {
+            turns.push(Turn {
+                first: record,
+                tools: Vec::new(),
+                total_tokens: 0,
+                cost: 0.0,
+            });
+        }
+        if record.dedup_key.is_none() {
+            // Keyed turns get their usage in the pass below.
+            if let Some(metrics) = &record.metrics {
+                turns[index].total_tokens += metrics.total_tokens;
+                turns[index].cost += metrics.cost;
+            }
+        }
+        for tool_use in &record.tool_uses {
+            if seen_calls.insert(&tool_use.id) {
+                turns[index].tools.push(tool_use);
+            }
+        }
+    }
+    for record in records {
+        let (Some(key), Some(metrics)) = (record.dedup_key.as_deref(), &record.metrics) else {
+            continue;
+        };
+        if record.duplicate {
+            continue;
+        }
+        if let Some(&index) = turn_index.get(key) {
+            turns[index].total_tokens += metrics.total_tokens;
+            turns[index].cost += metrics.cost;
+        }
+    }
+    turns.retain(|turn| !turn.tools.is_empty());
+
+    let mut tools: HashMap<&str, ToolTotals> = HashMap::new();
+    let mut projects: HashMap<&str, BreakdownTotals> = HashMap::new();
+    let mut daily: BTreeMap<String, BreakdownTotals> = BTreeMap::new();
+    for turn in &turns {
+        let record = turn.first;
+        let share = 1.0 / turn.tools.len() as f64;
+        let date = local_date(record, tz);
+        for tool_use in &turn.tools {
+            let name: &str = &tool_use.name;
+            let is_error = failed.get(&*tool_use.id).copied().unwrap_or(false);
+
+            let totals = tools.entry(name).or_default();
+            totals.calls += 1;
+            totals.errors += is_error as usize;
+            if let Some(session_id) = record.session_id.as_deref() {
+                totals.sessions.insert(session_id);
+            }
+            totals.projects.insert(&record.project);
+            totals.total_tokens += turn.total_tokens as f64 * share;
+            totals.cost += turn.cost * share;
+            let timestamp = record.timestamp.as_str();
+            if totals.first_used.is_none_or(|first| timestamp < first) {
+                totals.first_used = Some(timestamp);
+            }
+            if totals.last_used.is_none_or(|last| timestamp > last) {
+                totals.last_used = Some(timestamp);
+            }
+
+            for breakdown in [
+                projects.entry(&record.project).or_default(),
+                daily.entry(date.clone()).or_default(),
+            ] {
+                breakdown.calls += 1;
+                breakdown.errors += is_error as usize;
+                *breakdown.tools.entry(name).or_default() += 1;
+            }
+        }
+    }
+
+    let mut tool_stats: Vec<ToolStats> = tools
+        .iter()
+        .map(|(name, totals)| ToolStats {
+            name: name.to_string(),
+            mcp_server: mcp_server(name),
+            calls: totals.calls,
+            errors: totals.errors,
+            error_rate: totals.errors as f64 / totals.calls as f64,
+            sessions: totals.sessions.len(),
+            projects: totals.projects.len(),
+            total_tokens: totals.total_tokens.round() as u64,
+            cost: format!("{:.4}", totals.cost),
+            first_used: totals.first_used.unwrap_or_default().to_string(),
+            last_used: totals.last_used.unwrap_or_default().to_string(),
+        })
+        .collect();
+    tool_stats.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+
+    let mut project_stats: Vec<ToolBreakdown> = projects
+        .iter()
+        .map(|(project, totals)| totals.to_breakdown(Some(project), None))
+        .collect();
+    project_stats.sort_by(|a, b| {
+        b.calls
+            .cmp(&a.calls)
+            .then_with(|| a.project.cmp(&b.project))
+    });
+
+    let daily_stats = daily
+        .iter()
+        .map(|(date, totals)| totals.to_breakdown(None, Some(date)))
+        .collect();
+
+    turns.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+    let top_turns = turns
+        .iter()
+        .take(TOP_TURNS)
+        .map(|turn| ToolTurn {
+            session_id: turn.first.session_id.as_deref().map(String::from),
+            project: turn.first.project.to_string(),
+            timestamp: turn.first.timestamp.clone(),
+            model: turn.first.model.to_string(),
+            tools: turn.tools.iter().map(|t| t.name.to_string()).collect(),
+            total_tokens: turn.total_tokens,
+            cost: format!("{:.4}", turn.cost),
+        })
+        .collect();
+
+    let total_calls: usize = tool_stats.iter().map(|t| t.calls).sum();
+    let total_errors: usize = tool_stats.iter().map(|t| t.errors).sum();
+    ToolReport {
+        total_calls,
+        total_errors,
+        error_rate: total_errors as f64 / total_calls.max(1) as f64,
+        unique_tools: tool_stats.len(),
+        turns: turns.len(),
+        tools: tool_stats,
+        projects: project_stats,
+        daily: daily_stats,
+        top_turns,
+    }
+}
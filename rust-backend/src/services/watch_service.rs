@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::state::AppState;
+
+/// How long to wait for a burst of writes to settle before rescanning.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches `projects_path` and rescans the usage cache whenever session files
+/// change, so subscribers receive deltas as Claude sessions run.
+pub fn spawn_watcher(state: Arc<AppState>) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if !matches!(event.kind, EventKind::Access(_)) {
+                let _ = tx.send(());
+            }
+        }
+    })
+    .context("Failed to create file watcher")?;
+
+    let projects_path = state.config.projects_path.clone();
+    watcher
+        .watch(Path::new(&projects_path), RecursiveMode::Recursive)
+        .context(format!("Failed to watch {}", projects_path))?;
+
+    tokio::spawn(async move {
+        // The watcher stops when dropped; keep it alive with the task.
+        let _watcher = watcher;
+
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            let state = state.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                state
+                    .cache
+                    .sync(&state.config.projects_path, &state.pricing)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("Failed to refresh usage after file change: {:#}", e),
+                Err(e) => tracing::warn!("Usage refresh task failed: {}", e),
+            }
+        }
+    });
+
+    tracing::info!("Watching {} for session changes", projects_path);
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::models::MessageRecord;
+use crate::services::{
+    budget_statuses, AllProjectData, BudgetAlerts, BudgetStatus, McpLogCache, McpReport,
+    PricingTable, TimeWindow, UsageCache, UsageSnapshot, UsageStore,
+};
+
+/// Shared state handed to every route handler.
+pub struct AppState {
+    pub config: Config,
+    pub pricing: PricingTable,
+    pub cache: UsageCache,
+    /// Default timezone for routes that take a `tz` parameter.
+    pub tz: Tz,
+    pub mcp_logs: McpLogCache,
+    pub budget_alerts: BudgetAlerts,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Result<Self> {
+        let tz = config.timezone;
+        let pricing = PricingTable::load(config.pricing_file.as_deref())?;
+        let store = config
+            .index_path
+            .as_deref()
+            .map(UsageStore::open)
+            .transpose()?;
+        let cache = UsageCache::new(
+            Duration::from_secs(config.cache_ttl_secs),
+            store,
+            tz,
+            config.dedup_messages,
+        );
+        cache.load_store(&pricing)?;
+        let mcp_logs = McpLogCache::new(Duration::from_secs(config.mcp_cache_ttl_secs));
+        Ok(AppState {
+            config,
+            pricing,
+            cache,
+            tz,
+            mcp_logs,
+            budget_alerts: BudgetAlerts::default(),
+        })
+    }
+
+    /// The cached session data. When it is older than the TTL, the projects
+    /// directory is rescanned on a blocking thread first.
+    pub async fn usage_snapshot(self: &Arc<Self>) -> Result<Arc<UsageSnapshot>> {
+        if let Some(snapshot) = self.cache.fresh_snapshot() {
+            return Ok(snapshot);
+        }
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || {
+            state
+                .cache
+                .refresh(&state.config.projects_path, &state.pricing)
+        })
+        .await?
+    }
+
+    /// Aggregated usage across all projects for the messages in `window`,
+    /// with days and months counted in `tz`. Served from the cache when the
+    /// session files have not changed; other windows and timezones are
+    /// aggregated on a blocking thread, from the usage index when one is
+    /// configured and the window is bounded.
+    pub async fn usage_data(
+        self: &Arc<Self>,
+        tz: Tz,
+        window: &TimeWindow,
+    ) -> Result<Arc<AllProjectData>> {
+        let snapshot = self.usage_snapshot().await?;
+        if let Some(data) = snapshot.cached_data(tz, window) {
+            return Ok(data);
+        }
+        let window = *window;
+        let state = self.clone();
+        let data = tokio::task::spawn_blocking(move || {
+            state
+                .cache
+                .aggregate(&snapshot, tz, &window, &state.pricing)
+        })
+        .await?;
+        Ok(Arc::new(data))
+    }
+
+    /// Runs `f` over every counted message record on a blocking thread, for
+    /// views that need more than the prebuilt aggregates.
+    pub async fn scan_records<T, F>(self: &Arc<Self>, f: F) -> Result<T>
+    where
+        F: FnOnce(&[&MessageRecord]) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let snapshot = self.usage_snapshot().await?;
+        Ok(tokio::task::spawn_blocking(move || f(&snapshot.records(false))).await?)
+    }
+
+    /// Like [`scan_records`](Self::scan_records), including records dropped
+    /// as duplicates.
+    pub async fn scan_all_records<T, F>(self: &Arc<Self>, f: F) -> Result<T>
+    where
+        F: FnOnce(&[&MessageRecord]) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let snapshot = self.usage_snapshot().await?;
+        Ok(tokio::task::spawn_blocking(move || f(&snapshot.records(true))).await?)
+    }
+
+    /// Tool usage and log listing read from the MCP logs in the Claude CLI
+    /// cache directory. When the last report is older than the TTL, the
+    /// logs are re-read on a blocking thread first.
+    pub async fn mcp_report(self: &Arc<Self>) -> Result<Arc<McpReport>> {
+        if let Some(report) = self.mcp_logs.fresh() {
+            return Ok(report);
+        }
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || state.mcp_logs.refresh(&state.config.claude_cache_path))
+            .await?
+    }
+
+    /// Spending against each configured budget in the period containing
+    /// `now`, with periods in the configured timezone.
+    pub async fn budget_statuses(
+        self: &Arc<Self>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<BudgetStatus>> {
+        let state = self.clone();
+        self.scan_records(move |records| {
+            budget_statuses(&state.config.budgets, records, state.tz, now)
+        })
+        .await
+    }
+}
@@ -0,0 +1,199 @@
+//! Sequential vs parallel ingestion benchmark over rust-backend's own code.
+//!
+//! The parallel run is the backend's usage cache doing its first rescan:
+//! session files parsed with rayon, then partial aggregates built per fixed
+//! chunk of records and merged in path order. The sequential run reads the
+//! same files one after another with the backend's `FileCursor` and folds
+//! every record into a single `UsageAggregates`, so both must report the
+//! same totals.
+
+use chrono_tz::Tz;
+use rust_backend::services::{
+    AllProjectData, FileCursor, IngestMetrics, PricingTable, TailOutcome, TimeWindow,
+    UsageAggregates, UsageCache, list_project_dirs, list_session_files, project_name,
+};
+use serde_json::json;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn ingest_sequential(root: &str, pricing: &PricingTable) -> AllProjectData {
+    let project_dirs = list_project_dirs(root).unwrap();
+    let project_names: Vec<String> = project_dirs.iter().map(|dir| project_name(dir)).collect();
+
+    let mut files: Vec<(PathBuf, Arc<str>)> = Vec::new();
+    for project_dir in &project_dirs {
+        let project: Arc<str> = project_name(project_dir).into();
+        for file in list_session_files(project_dir).unwrap() {
+            files.push((file, project.clone()));
+        }
+    }
+    // The cache aggregates in path order too.
+    files.sort();
+
+    let metrics = IngestMetrics::default();
+    let mut aggregates = UsageAggregates::new(&project_names, Tz::UTC);
+    for (file, project) in &files {
+        let outcome = FileCursor::default()
+            .read_appended(file, project, pricing, &metrics)
+            .unwrap();
+        if let TailOutcome::Appended(records) = outcome {
+            for record in &records {
+                aggregates.add(record);
+            }
+        }
+    }
+    aggregates.to_data()
+}
+
+fn ingest_parallel(root: &str, pricing: &PricingTable) -> Arc<AllProjectData> {
+    let cache = UsageCache::new(Duration::ZERO, None, Tz::UTC, false);
+    let snapshot = cache.refresh(root, pricing).unwrap();
+    snapshot
+        .cached_data(Tz::UTC, &TimeWindow::default())
+        .unwrap()
+}
+
+/// Everything the API reports from the aggregates, for comparing runs.
+fn fingerprint(data: &AllProjectData) -> serde_json::Value {
+    json!({
+        "daily": data.daily_usage,
+        "monthly": data.monthly_usage,
+        "models": data.model_usage,
+        "projects": data.projects,
+        "sessions": data.total_sessions,
+    })
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Writes a deterministic synthetic corpus shaped like `~/.claude/projects`.
+fn generate_corpus(root: &Path, projects: usize, files: usize, lines: usize) {
+    const MODELS: [&str; 3] = [
+        "claude-sonnet-4-5-20250929",
+        "claude-opus-4-1-20250805",
+        "claude-haiku-4-5-20251001",
+    ];
+
+    let _ = fs::remove_dir_all(root);
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    for p in 0..projects {
+        let dir = root.join(format!("-home-user-project-{:03}", p));
+        fs::create_dir_all(&dir).unwrap();
+
+        for f in 0..files {
+            let session_id = format!("{:08x}-bench-{:03}-{:03}", next() as u32, p, f);
+            let file = fs::File::create(dir.join(format!("{}.jsonl", session_id))).unwrap();
+            let mut out = BufWriter::new(file);
+
+            for l in 0..lines {
+                let day = 1 + (next() % 28);
+                let month = 1 + (next() % 12);
+                let line = json!({
+                    "type": "assistant",
+                    "sessionId": session_id,
+                    "requestId": format!("req_{:016x}", next()),
+                    "timestamp": format!("2025-{:02}-{:02}T{:02}:{:02}:00.000Z", month, day, next() % 24, l % 60),
+                    "message": {
+                        "id": format!("msg_{:016x}", next()),
+                        "model": MODELS[(next() % 3) as usize],
+                        "usage": {
+                            "input_tokens": next() % 500,
+                            "output_tokens": next() % 2000,
+                            "cache_creation_input_tokens": next() % 5000,
+                            "cache_read_input_tokens": next() % 50000,
+                        }
+                    }
+                });
+                writeln!(out, "{}", line).unwrap();
+            }
+        }
+    }
+}
+
+pub fn run() {
+    let projects = env_usize("BENCH_PROJECTS", 20);
+    let files_per_project = env_usize("BENCH_FILES", 25);
+    let lines = env_usize("BENCH_LINES", 400);
+    let root = std::env::temp_dir().join("rust-benchmark-corpus");
+    let root_str = root.to_str().unwrap();
+    let pricing = PricingTable::builtin();
+
+    println!("\n{}", "=".repeat(60));
+    println!("Ingestion Benchmark: rust-backend sequential vs parallel");
+    println!("{}", "=".repeat(60));
+    println!(
+        "Corpus: {} projects x {} files x {} lines -> {}",
+        projects,
+        files_per_project,
+        lines,
+        root.display()
+    );
+
+    let start = Instant::now();
+    generate_corpus(&root, projects, files_per_project, lines);
+    println!(
+        "Generated corpus in {:.2}ms",
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+
+    // Warm the page cache so both runs read from memory.
+    let _ = ingest_sequential(root_str, &pricing);
+
+    let start = Instant::now();
+    let sequential = ingest_sequential(root_str, &pricing);
+    let sequential_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let start = Instant::now();
+    let parallel = ingest_parallel(root_str, &pricing);
+    let parallel_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let identical = fingerprint(&sequential) == fingerprint(&parallel);
+
+    println!("{}", "=".repeat(60));
+    println!(
+        "Threads:                    {}",
+        rayon::current_num_threads()
+    );
+    println!(
+        "Files:                      {}",
+        projects * files_per_project
+    );
+    println!("Sequential ingestion:       {:.2}ms", sequential_ms);
+    println!("Parallel ingestion:         {:.2}ms", parallel_ms);
+    println!(
+        "Speedup:                    {:.2}x",
+        sequential_ms / parallel_ms.max(f64::EPSILON)
+    );
+    println!(
+        "Results identical:          {}",
+        if identical { "yes" } else { "NO" }
+    );
+    println!(
+        "Days / months / models:     {} / {} / {}",
+        parallel.daily_usage.len(),
+        parallel.monthly_usage.len(),
+        parallel.model_usage.len()
+    );
+    println!("{}", "=".repeat(60));
+    println!();
+
+    let _ = fs::remove_dir_all(&root);
+    if !identical {
+        std::process::exit(1);
+    }
+}
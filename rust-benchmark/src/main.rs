@@ -1,6 +1,5 @@
-use chrono::{DateTime, Utc};
-use glob::glob;
-use rayon::prelude::*;
+mod ingest;
+
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
@@ -48,6 +47,7 @@ struct UsageMetrics {
 
 #[derive(Debug)]
 struct DayData {
+    #[allow(dead_code)]
     date: String,
     input_tokens: u64,
     output_tokens: u64,
@@ -247,6 +247,7 @@ fn benchmark_json_parsing(projects_path: &str) -> (f64, (usize, usize)) {
     (total_time, (total_parsed, total_failed))
 }
 
+#[allow(clippy::collapsible_if)]
 fn benchmark_data_aggregation(projects_path: &str) -> (f64, (usize, usize, usize)) {
     let mut profiler = PerformanceProfiler::new("Data Aggregation");
 
@@ -368,6 +369,12 @@ fn benchmark_data_aggregation(projects_path: &str) -> (f64, (usize, usize, usize
 }
 
 fn main() {
+    // `rust-benchmark ingest` compares sequential and parallel ingestion
+    if std::env::args().nth(1).as_deref() == Some("ingest") {
+        ingest::run();
+        return;
+    }
+
     println!("\n{}", "=".repeat(60));
     println!("Starting Comprehensive Performance Benchmark (Rust)");
     println!("{}", "=".repeat(60));
@@ -0,0 +1,169 @@
+use crate::{DailyUsage, DetailedUsage, ModelUsage, MonthlyUsage, ProcessedData, Project};
+use anyhow::Result;
+use clap::ValueEnum;
+use rust_backend::{
+    models,
+    services::{ColumnType, ExportRow, ExportValue, write_csv, write_parquet},
+};
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Every table in one JSON document
+    Json,
+    Csv,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Table {
+    Daily,
+    Monthly,
+    Models,
+    Projects,
+    /// One row per message with usage
+    Messages,
+}
+
+impl Table {
+    /// Writes this table of `data` to `out`.
+    pub fn write(
+        self,
+        data: &ProcessedData,
+        format: OutputFormat,
+        out: impl Write + Send,
+    ) -> Result<()> {
+        match self {
+            Table::Daily => write_rows(&data.daily_usage, format, out),
+            Table::Monthly => write_rows(&data.monthly_usage, format, out),
+            Table::Models => write_rows(&data.model_usage, format, out),
+            Table::Projects => write_rows(&data.projects, format, out),
+            Table::Messages => write_rows(&data.detailed_usage, format, out),
+        }
+    }
+}
+
+fn write_rows<R: ExportRow + Serialize>(
+    rows: &[R],
+    format: OutputFormat,
+    mut out: impl Write + Send,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut out, rows)?;
+            writeln!(out)?;
+            out.flush()?;
+            Ok(())
+        }
+        OutputFormat::Csv => write_csv(rows, out),
+        OutputFormat::Parquet => write_parquet(rows, out),
+    }
+}
+
+// Tables and columns are the backend's, so files written here match its
+// `/api/v2/export` downloads column for column.
+
+impl ExportRow for DailyUsage {
+    const TABLE: &'static str = <models::DailyUsage as ExportRow>::TABLE;
+    const COLUMNS: &'static [(&'static str, ColumnType)] =
+        <models::DailyUsage as ExportRow>::COLUMNS;
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.date)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for MonthlyUsage {
+    const TABLE: &'static str = <models::MonthlyUsage as ExportRow>::TABLE;
+    const COLUMNS: &'static [(&'static str, ColumnType)] =
+        <models::MonthlyUsage as ExportRow>::COLUMNS;
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.month)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.messages as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for ModelUsage {
+    const TABLE: &'static str = <models::ModelUsage as ExportRow>::TABLE;
+    const COLUMNS: &'static [(&'static str, ColumnType)] =
+        <models::ModelUsage as ExportRow>::COLUMNS;
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.model)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost_usd),
+            ExportValue::Int(self.sessions as u64),
+            ExportValue::Int(self.messages as u64),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
+
+impl ExportRow for Project {
+    const TABLE: &'static str = <models::ProjectData as ExportRow>::TABLE;
+    const COLUMNS: &'static [(&'static str, ColumnType)] =
+        <models::ProjectData as ExportRow>::COLUMNS;
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.name)),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.total_cost_usd),
+            ExportValue::Int(self.message_count as u64),
+            ExportValue::Text(self.last_activity.as_deref()),
+        ]
+    }
+}
+
+impl ExportRow for DetailedUsage {
+    const TABLE: &'static str = <models::MessageRecord as ExportRow>::TABLE;
+    const COLUMNS: &'static [(&'static str, ColumnType)] =
+        <models::MessageRecord as ExportRow>::COLUMNS;
+
+    fn values(&self) -> Vec<ExportValue<'_>> {
+        vec![
+            ExportValue::Text(Some(&self.timestamp)),
+            ExportValue::Text(Some(&self.project)),
+            ExportValue::Text(Some(&self.session_id)),
+            ExportValue::Text(Some(&self.model)),
+            ExportValue::Int(self.input_tokens),
+            ExportValue::Int(self.output_tokens),
+            ExportValue::Int(self.cached_tokens),
+            ExportValue::Int(self.total_tokens),
+            ExportValue::Float(self.cost),
+            ExportValue::Int(self.new_input_tokens),
+            ExportValue::Int(self.cache_creation_tokens),
+            ExportValue::Int(self.cache_read_tokens),
+        ]
+    }
+}
@@ -1,11 +1,18 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use clap::Parser;
+use export::{OutputFormat, Table};
+use rust_backend::services::TimeWindow;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
 
+mod export;
+
 /// Claude Usage Dashboard Data Processor
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -13,6 +20,60 @@ struct Args {
     /// Path to Claude projects directory
     #[arg(short, long)]
     projects_path: String,
+
+    /// Output format; csv and parquet write a single table
+    #[arg(long, value_enum, default_value = "json")]
+    format: OutputFormat,
+
+    /// Table to write; required for csv and parquet
+    #[arg(long, value_enum, required_if_eq_any = [("format", "csv"), ("format", "parquet")])]
+    table: Option<Table>,
+
+    /// File to write to instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Only count messages sent at or after this date or ISO 8601 datetime
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Only count messages sent before this datetime, or on or before this date
+    #[arg(long)]
+    to: Option<String>,
+
+    /// IANA timezone `--from` and `--to` are read in when they have no offset
+    #[arg(long, value_name = "TZ", default_value = "UTC")]
+    timezone: Tz,
+
+    /// Only count messages from this project directory
+    #[arg(long)]
+    project: Option<String>,
+
+    /// Only count messages from this model
+    #[arg(long)]
+    model: Option<String>,
+}
+
+/// Which messages are counted, for every output format.
+struct Filter {
+    window: TimeWindow,
+    project: Option<String>,
+    model: Option<String>,
+}
+
+impl Filter {
+    /// The window is the backend's, so `--from`/`--to` keep the same
+    /// messages as `from`/`to` on `/api/v2/export`.
+    fn matches(&self, timestamp: Option<&str>, model: Option<&str>) -> bool {
+        let sent_at = timestamp
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc));
+        self.window.contains_time(sent_at)
+            && self
+                .model
+                .as_deref()
+                .is_none_or(|wanted| model == Some(wanted))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,6 +128,9 @@ struct DailyUsage {
     #[serde(rename = "totalTokens")]
     total_tokens: u64,
     cost: String,
+    /// `cost` before rounding, for CSV and Parquet.
+    #[serde(skip)]
+    cost_usd: f64,
     sessions: usize,
     #[serde(rename = "newInputTokens")]
     new_input_tokens: u64,
@@ -89,6 +153,9 @@ struct MonthlyUsage {
     total_tokens: u64,
     messages: usize,
     cost: String,
+    /// `cost` before rounding, for CSV and Parquet.
+    #[serde(skip)]
+    cost_usd: f64,
     sessions: usize,
     #[serde(rename = "newInputTokens")]
     new_input_tokens: u64,
@@ -111,6 +178,9 @@ struct ModelUsage {
     total_tokens: u64,
     messages: usize,
     cost: String,
+    /// `cost` before rounding, for CSV and Parquet.
+    #[serde(skip)]
+    cost_usd: f64,
     sessions: usize,
     #[serde(rename = "newInputTokens")]
     new_input_tokens: u64,
@@ -128,6 +198,9 @@ struct Project {
     total_tokens: u64,
     #[serde(rename = "totalCost")]
     total_cost: String,
+    /// `total_cost` before rounding, for CSV and Parquet.
+    #[serde(skip)]
+    total_cost_usd: f64,
     #[serde(rename = "messageCount")]
     message_count: usize,
     #[serde(rename = "lastActivity")]
@@ -137,6 +210,7 @@ struct Project {
 #[derive(Debug, Serialize)]
 struct DetailedUsage {
     timestamp: String,
+    project: String,
     #[serde(rename = "sessionId")]
     session_id: String,
     model: String,
@@ -241,7 +315,7 @@ fn calculate_usage_metrics(usage: &Usage, _model: Option<&str>) -> UsageMetrics
     }
 }
 
-fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
+fn process_project_data(projects_path: &str, filter: &Filter) -> Result<ProcessedData> {
     let project_dirs: Vec<PathBuf> = fs::read_dir(projects_path)
         .context("Failed to read projects directory")?
         .filter_map(|entry| entry.ok())
@@ -261,9 +335,19 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
             .and_then(|n| n.to_str())
             .unwrap_or("Unknown")
             .to_string();
+        if filter
+            .project
+            .as_ref()
+            .is_some_and(|project| *project != project_name)
+        {
+            continue;
+        }
 
         let files: Vec<PathBuf> = fs::read_dir(project_dir)
-            .context(format!("Failed to read project directory: {:?}", project_dir))?
+            .context(format!(
+                "Failed to read project directory: {:?}",
+                project_dir
+            ))?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|path| {
@@ -280,8 +364,8 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
         let mut last_activity: Option<String> = None;
 
         for file in files {
-            let content = fs::read_to_string(&file)
-                .context(format!("Failed to read file: {:?}", file))?;
+            let content =
+                fs::read_to_string(&file).context(format!("Failed to read file: {:?}", file))?;
 
             for line in content.lines() {
                 let trimmed = line.trim();
@@ -294,136 +378,142 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
                     Err(_) => continue,
                 };
 
+                let model = msg.message.as_ref().and_then(|m| m.model.as_deref());
+                if !filter.matches(msg.timestamp.as_deref(), model) {
+                    continue;
+                }
+
                 message_count += 1;
 
-                if let Some(timestamp) = &msg.timestamp {
-                    if last_activity.is_none()
-                        || timestamp > last_activity.as_ref().unwrap()
-                    {
-                        last_activity = Some(timestamp.clone());
-                    }
+                if let Some(timestamp) = &msg.timestamp
+                    && (last_activity.is_none() || timestamp > last_activity.as_ref().unwrap())
+                {
+                    last_activity = Some(timestamp.clone());
                 }
 
                 if let (Some(message_content), Some(timestamp)) =
                     (msg.message, msg.timestamp.as_ref())
+                    && let Some(usage) = message_content.usage
                 {
-                    if let Some(usage) = message_content.usage {
-                        let model = message_content.model.clone().unwrap_or_else(|| "unknown".to_string());
-                        let metrics = calculate_usage_metrics(&usage, Some(&model));
-
-                        // Detailed usage
-                        if let Some(session_id) = &msg.session_id {
-                            detailed_usage.push(DetailedUsage {
-                                timestamp: timestamp.clone(),
-                                session_id: session_id.clone(),
-                                model: model.clone(),
-                                input_tokens: metrics.input_tokens,
-                                output_tokens: metrics.output_tokens,
-                                cached_tokens: metrics.cached_tokens,
-                                total_tokens: metrics.total_tokens,
-                                cost: metrics.cost,
-                                new_input_tokens: metrics.new_input_tokens,
-                                cache_creation_tokens: metrics.cache_creation_tokens,
-                                cache_read_tokens: metrics.cache_read_tokens,
-                            });
-                        }
-
-                        // Daily data
-                        let date = timestamp.split('T').next().unwrap_or("").to_string();
-                        let day_data = usage_by_date.entry(date.clone()).or_insert(DayData {
-                            date: date.clone(),
-                            input_tokens: 0,
-                            output_tokens: 0,
-                            cached_tokens: 0,
-                            total_tokens: 0,
-                            cost: 0.0,
-                            sessions: HashSet::new(),
-                            new_input_tokens: 0,
-                            cache_creation_tokens: 0,
-                            cache_read_tokens: 0,
+                    let model = message_content
+                        .model
+                        .clone()
+                        .unwrap_or_else(|| "unknown".to_string());
+                    let metrics = calculate_usage_metrics(&usage, Some(&model));
+
+                    // Detailed usage
+                    if let Some(session_id) = &msg.session_id {
+                        detailed_usage.push(DetailedUsage {
+                            timestamp: timestamp.clone(),
+                            project: project_name.clone(),
+                            session_id: session_id.clone(),
+                            model: model.clone(),
+                            input_tokens: metrics.input_tokens,
+                            output_tokens: metrics.output_tokens,
+                            cached_tokens: metrics.cached_tokens,
+                            total_tokens: metrics.total_tokens,
+                            cost: metrics.cost,
+                            new_input_tokens: metrics.new_input_tokens,
+                            cache_creation_tokens: metrics.cache_creation_tokens,
+                            cache_read_tokens: metrics.cache_read_tokens,
                         });
+                    }
 
-                        day_data.input_tokens += metrics.input_tokens;
-                        day_data.output_tokens += metrics.output_tokens;
-                        day_data.cached_tokens += metrics.cached_tokens;
-                        day_data.total_tokens += metrics.total_tokens;
-                        day_data.cost += metrics.cost;
-                        day_data.new_input_tokens += metrics.new_input_tokens;
-                        day_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        day_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            day_data.sessions.insert(session_id.clone());
-                        }
-
-                        // Monthly data
-                        let month = if date.len() >= 7 {
-                            format!("{}-{}", &date[..4], &date[5..7])
-                        } else {
-                            "unknown".to_string()
-                        };
-
-                        let month_data = usage_by_month.entry(month.clone()).or_insert(MonthData {
-                            month: month.clone(),
-                            input_tokens: 0,
-                            output_tokens: 0,
-                            cached_tokens: 0,
-                            total_tokens: 0,
-                            cost: 0.0,
-                            sessions: HashSet::new(),
-                            messages: 0,
-                            new_input_tokens: 0,
-                            cache_creation_tokens: 0,
-                            cache_read_tokens: 0,
-                        });
+                    // Daily data
+                    let date = timestamp.split('T').next().unwrap_or("").to_string();
+                    let day_data = usage_by_date.entry(date.clone()).or_insert(DayData {
+                        date: date.clone(),
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        cached_tokens: 0,
+                        total_tokens: 0,
+                        cost: 0.0,
+                        sessions: HashSet::new(),
+                        new_input_tokens: 0,
+                        cache_creation_tokens: 0,
+                        cache_read_tokens: 0,
+                    });
+
+                    day_data.input_tokens += metrics.input_tokens;
+                    day_data.output_tokens += metrics.output_tokens;
+                    day_data.cached_tokens += metrics.cached_tokens;
+                    day_data.total_tokens += metrics.total_tokens;
+                    day_data.cost += metrics.cost;
+                    day_data.new_input_tokens += metrics.new_input_tokens;
+                    day_data.cache_creation_tokens += metrics.cache_creation_tokens;
+                    day_data.cache_read_tokens += metrics.cache_read_tokens;
+
+                    if let Some(session_id) = &msg.session_id {
+                        day_data.sessions.insert(session_id.clone());
+                    }
 
-                        month_data.input_tokens += metrics.input_tokens;
-                        month_data.output_tokens += metrics.output_tokens;
-                        month_data.cached_tokens += metrics.cached_tokens;
-                        month_data.total_tokens += metrics.total_tokens;
-                        month_data.cost += metrics.cost;
-                        month_data.messages += 1;
-                        month_data.new_input_tokens += metrics.new_input_tokens;
-                        month_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        month_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            month_data.sessions.insert(session_id.clone());
-                        }
-
-                        // Model data
-                        let model_data = usage_by_model.entry(model.clone()).or_insert(ModelData {
-                            model: model.clone(),
-                            input_tokens: 0,
-                            output_tokens: 0,
-                            cached_tokens: 0,
-                            total_tokens: 0,
-                            cost: 0.0,
-                            sessions: HashSet::new(),
-                            messages: 0,
-                            new_input_tokens: 0,
-                            cache_creation_tokens: 0,
-                            cache_read_tokens: 0,
-                        });
+                    // Monthly data
+                    let month = if date.len() >= 7 {
+                        format!("{}-{}", &date[..4], &date[5..7])
+                    } else {
+                        "unknown".to_string()
+                    };
+
+                    let month_data = usage_by_month.entry(month.clone()).or_insert(MonthData {
+                        month: month.clone(),
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        cached_tokens: 0,
+                        total_tokens: 0,
+                        cost: 0.0,
+                        sessions: HashSet::new(),
+                        messages: 0,
+                        new_input_tokens: 0,
+                        cache_creation_tokens: 0,
+                        cache_read_tokens: 0,
+                    });
+
+                    month_data.input_tokens += metrics.input_tokens;
+                    month_data.output_tokens += metrics.output_tokens;
+                    month_data.cached_tokens += metrics.cached_tokens;
+                    month_data.total_tokens += metrics.total_tokens;
+                    month_data.cost += metrics.cost;
+                    month_data.messages += 1;
+                    month_data.new_input_tokens += metrics.new_input_tokens;
+                    month_data.cache_creation_tokens += metrics.cache_creation_tokens;
+                    month_data.cache_read_tokens += metrics.cache_read_tokens;
+
+                    if let Some(session_id) = &msg.session_id {
+                        month_data.sessions.insert(session_id.clone());
+                    }
 
-                        model_data.input_tokens += metrics.input_tokens;
-                        model_data.output_tokens += metrics.output_tokens;
-                        model_data.cached_tokens += metrics.cached_tokens;
-                        model_data.total_tokens += metrics.total_tokens;
-                        model_data.cost += metrics.cost;
-                        model_data.messages += 1;
-                        model_data.new_input_tokens += metrics.new_input_tokens;
-                        model_data.cache_creation_tokens += metrics.cache_creation_tokens;
-                        model_data.cache_read_tokens += metrics.cache_read_tokens;
-
-                        if let Some(session_id) = &msg.session_id {
-                            model_data.sessions.insert(session_id.clone());
-                        }
-
-                        // Project totals
-                        total_tokens += metrics.total_tokens;
-                        total_cost += metrics.cost;
+                    // Model data
+                    let model_data = usage_by_model.entry(model.clone()).or_insert(ModelData {
+                        model: model.clone(),
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        cached_tokens: 0,
+                        total_tokens: 0,
+                        cost: 0.0,
+                        sessions: HashSet::new(),
+                        messages: 0,
+                        new_input_tokens: 0,
+                        cache_creation_tokens: 0,
+                        cache_read_tokens: 0,
+                    });
+
+                    model_data.input_tokens += metrics.input_tokens;
+                    model_data.output_tokens += metrics.output_tokens;
+                    model_data.cached_tokens += metrics.cached_tokens;
+                    model_data.total_tokens += metrics.total_tokens;
+                    model_data.cost += metrics.cost;
+                    model_data.messages += 1;
+                    model_data.new_input_tokens += metrics.new_input_tokens;
+                    model_data.cache_creation_tokens += metrics.cache_creation_tokens;
+                    model_data.cache_read_tokens += metrics.cache_read_tokens;
+
+                    if let Some(session_id) = &msg.session_id {
+                        model_data.sessions.insert(session_id.clone());
                     }
+
+                    // Project totals
+                    total_tokens += metrics.total_tokens;
+                    total_cost += metrics.cost;
                 }
             }
         }
@@ -433,6 +523,7 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
             path: project_dir.to_string_lossy().to_string(),
             total_tokens,
             total_cost: format!("{:.4}", total_cost),
+            total_cost_usd: total_cost,
             message_count,
             last_activity,
         });
@@ -440,14 +531,15 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
 
     // Convert to output format
     let mut daily_usage: Vec<DailyUsage> = usage_by_date
-        .into_iter()
-        .map(|(_, day)| DailyUsage {
+        .into_values()
+        .map(|day| DailyUsage {
             date: day.date,
             input_tokens: day.input_tokens,
             output_tokens: day.output_tokens,
             cached_tokens: day.cached_tokens,
             total_tokens: day.total_tokens,
             cost: format!("{:.4}", day.cost),
+            cost_usd: day.cost,
             sessions: day.sessions.len(),
             new_input_tokens: day.new_input_tokens,
             cache_creation_tokens: day.cache_creation_tokens,
@@ -457,8 +549,8 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
     daily_usage.sort_by(|a, b| a.date.cmp(&b.date));
 
     let mut monthly_usage: Vec<MonthlyUsage> = usage_by_month
-        .into_iter()
-        .map(|(_, month)| MonthlyUsage {
+        .into_values()
+        .map(|month| MonthlyUsage {
             month: month.month,
             input_tokens: month.input_tokens,
             output_tokens: month.output_tokens,
@@ -466,6 +558,7 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
             total_tokens: month.total_tokens,
             messages: month.messages,
             cost: format!("{:.4}", month.cost),
+            cost_usd: month.cost,
             sessions: month.sessions.len(),
             new_input_tokens: month.new_input_tokens,
             cache_creation_tokens: month.cache_creation_tokens,
@@ -475,8 +568,8 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
     monthly_usage.sort_by(|a, b| a.month.cmp(&b.month));
 
     let mut model_usage: Vec<ModelUsage> = usage_by_model
-        .into_iter()
-        .map(|(_, model)| ModelUsage {
+        .into_values()
+        .map(|model| ModelUsage {
             model: model.model,
             input_tokens: model.input_tokens,
             output_tokens: model.output_tokens,
@@ -484,19 +577,16 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
             total_tokens: model.total_tokens,
             messages: model.messages,
             cost: format!("{:.4}", model.cost),
+            cost_usd: model.cost,
             sessions: model.sessions.len(),
             new_input_tokens: model.new_input_tokens,
             cache_creation_tokens: model.cache_creation_tokens,
             cache_read_tokens: model.cache_read_tokens,
         })
         .collect();
-    model_usage.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
+    model_usage.sort_by_key(|model| Reverse(model.total_tokens));
 
-    projects.sort_by(|a, b| {
-        b.last_activity
-            .as_ref()
-            .cmp(&a.last_activity.as_ref())
-    });
+    projects.sort_by(|a, b| b.last_activity.as_ref().cmp(&a.last_activity.as_ref()));
 
     detailed_usage.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
 
@@ -519,13 +609,34 @@ fn process_project_data(projects_path: &str) -> Result<ProcessedData> {
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let data = process_project_data(&args.projects_path)
+    let window = TimeWindow::parse(args.from.as_deref(), args.to.as_deref(), args.timezone)
+        .context("Invalid --from/--to")?;
+    let filter = Filter {
+        window,
+        project: args.project,
+        model: args.model,
+    };
+    let data = process_project_data(&args.projects_path, &filter)
         .context("Failed to process project data")?;
 
-    let json = serde_json::to_string(&data)
-        .context("Failed to serialize data to JSON")?;
+    let mut out: BufWriter<Box<dyn Write + Send>> = BufWriter::new(match &args.output {
+        Some(path) => Box::new(
+            File::create(path).context(format!("Failed to create output file: {:?}", path))?,
+        ),
+        None => Box::new(io::stdout()),
+    });
 
-    println!("{}", json);
+    match args.table {
+        Some(table) => table
+            .write(&data, args.format, &mut out)
+            .context(format!("Failed to write {:?} table", table))?,
+        None => {
+            let json = serde_json::to_string(&data)
+                .context("Failed to serialize data to JSON")?;
+            writeln!(out, "{}", json)?;
+        }
+    }
+    out.flush()?;
 
     Ok(())
 }